@@ -1,40 +1,144 @@
-import { Item, CreateItemDTO } from '../types';
+import {
+  Item,
+  CategoryRole,
+  CategorySummary,
+  CreateItemDTO,
+  ImageVariants,
+  RankSession,
+  RankChoice,
+} from '../types';
 
 // Backend returns these fields in snake_case
 interface BackendItem {
   id: string;
+  category_id: string;
   category: string;
   name: string;
   notes?: string | null;
   image_url?: string | null;
+  image_variants?: ImageVariants | null;
   created_at: string;
   rank_order: number;
   normalized_score: number;
 }
 
+interface BackendCategorySummary {
+  id: string;
+  name: string;
+  description: string | null;
+  icon: string | null;
+  cover_image_url: string | null;
+  position: number | null;
+  is_public: boolean;
+  share_slug: string | null;
+  created_at: string;
+  updated_at: string;
+  item_count: number;
+  ranked_count: number;
+  unranked_count: number;
+  top_item: BackendItem | null;
+  role: CategoryRole;
+}
+
+function transformCategorySummary(category: BackendCategorySummary): CategorySummary {
+  return {
+    id: category.id,
+    name: category.name,
+    description: category.description ?? undefined,
+    icon: category.icon ?? undefined,
+    coverImageUrl: category.cover_image_url ?? undefined,
+    position: category.position ?? undefined,
+    isPublic: category.is_public,
+    shareSlug: category.share_slug ?? undefined,
+    createdAt: new Date(category.created_at),
+    updatedAt: new Date(category.updated_at),
+    itemCount: category.item_count,
+    rankedCount: category.ranked_count,
+    unrankedCount: category.unranked_count,
+    topItem: category.top_item ? transformItem(category.top_item) : undefined,
+    role: category.role,
+  };
+}
+
+interface BackendRankSession {
+  id: string;
+  item: BackendItem;
+  comparison: BackendItem | null;
+  complete: boolean;
+}
+
+function transformRankSession(session: BackendRankSession): RankSession {
+  return {
+    id: session.id,
+    item: transformItem(session.item),
+    comparison: session.comparison ? transformItem(session.comparison) : undefined,
+    complete: session.complete,
+  };
+}
+
 function transformItem(item: BackendItem): Item {
   return {
     id: item.id,
+    categoryId: item.category_id,
     category: item.category,
     name: item.name,
     notes: item.notes ?? undefined,
     imageUrl: item.image_url ?? undefined, // Map snake_case to camelCase
+    imageVariants: item.image_variants ?? undefined,
     createdAt: new Date(item.created_at),
     rankOrder: item.rank_order,
     normalizedScore: item.normalized_score,
   };
 }
 
-async function fetchWithAuth(url: string, options: RequestInit = {}) {
+function clearTokens() {
+  localStorage.removeItem('token');
+  localStorage.removeItem('refreshToken');
+}
+
+// Shared so that concurrent requests hitting an expired token only refresh once
+let refreshing: Promise<boolean> | null = null;
+
+async function refreshTokens(): Promise<boolean> {
+  const refreshToken = localStorage.getItem('refreshToken');
+  if (!refreshToken) return false;
+
+  const res = await fetch('/api/token/refresh', {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify({ refresh_token: refreshToken }),
+  });
+  if (!res.ok) return false;
+
+  const data = await res.json();
+  localStorage.setItem('token', data.token);
+  localStorage.setItem('refreshToken', data.refresh_token);
+  return true;
+}
+
+async function fetchWithToken(url: string, options: RequestInit) {
   const token = localStorage.getItem('token');
   const headers = {
     ...options.headers,
     ...(token ? { Authorization: `Bearer ${token}` } : {}),
   } as HeadersInit;
 
-  const res = await fetch(url, { ...options, headers });
+  return fetch(url, { ...options, headers });
+}
+
+async function fetchWithAuth(url: string, options: RequestInit = {}) {
+  let res = await fetchWithToken(url, options);
   if (res.status === 401) {
-    localStorage.removeItem('token');
+    refreshing ??= refreshTokens().finally(() => {
+      refreshing = null;
+    });
+    if (await refreshing) {
+      res = await fetchWithToken(url, options);
+    }
+  }
+
+  if (res.status === 401) {
+    clearTokens();
     window.location.href = '/login';
     throw new Error('Unauthorized');
   }
@@ -66,6 +170,14 @@ async function fetchWithAuth(url: string, options: RequestInit = {}) {
 }
 
 export const api = {
+  logout: async (): Promise<void> => {
+    try {
+      await fetchWithToken('/api/logout', { method: 'POST' });
+    } finally {
+      clearTokens();
+    }
+  },
+
   getAllItems: async (): Promise<Item[]> => {
     const res = await fetchWithAuth('/api/items');
     if (!res.ok) throw new Error('Failed to fetch items');
@@ -91,6 +203,7 @@ export const api = {
   createItem: async (data: CreateItemDTO): Promise<Item> => {
     const payload = {
       category: data.category,
+      category_id: data.categoryId,
       name: data.name,
       notes: data.notes,
       image_url: data.imageUrl,
@@ -126,19 +239,33 @@ export const api = {
     return transformItem(updatedItem);
   },
 
-  getAllCategories: async (): Promise<string[]> => {
+  getAllCategories: async (): Promise<CategorySummary[]> => {
     const res = await fetchWithAuth('/api/categories');
     if (!res.ok) throw new Error('Failed to fetch categories');
-    return await res.json();
+    const data: BackendCategorySummary[] = await res.json();
+    return data.map(transformCategorySummary);
   },
 
-  updateItemRank: async (id: string, rankOrder: number): Promise<void> => {
-    const res = await fetchWithAuth(`/api/items/${id}`, {
-      method: 'PATCH',
+  startRankSession: async (itemId: string): Promise<RankSession> => {
+    const res = await fetchWithAuth('/api/rank-sessions', {
+      method: 'POST',
+      headers: { 'Content-Type': 'application/json' },
+      body: JSON.stringify({ item_id: itemId }),
+    });
+    if (!res.ok) throw new Error('Failed to start ranking');
+    const data: BackendRankSession = await res.json();
+    return transformRankSession(data);
+  },
+
+  answerRankSession: async (sessionId: string, prefer: RankChoice): Promise<RankSession> => {
+    const res = await fetchWithAuth(`/api/rank-sessions/${sessionId}/answer`, {
+      method: 'POST',
       headers: { 'Content-Type': 'application/json' },
-      body: JSON.stringify({ rank_order: rankOrder }),
+      body: JSON.stringify({ prefer }),
     });
-    if (!res.ok) throw new Error('Failed to update item rank');
+    if (!res.ok) throw new Error('Failed to save ranking choice');
+    const data: BackendRankSession = await res.json();
+    return transformRankSession(data);
   },
 
   uploadImage: async (file: File): Promise<string> => {
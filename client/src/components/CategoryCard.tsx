@@ -1,11 +1,14 @@
 import { Link } from 'react-router-dom';
 import { Folder, ChevronRight } from 'lucide-react';
+import { CategorySummary } from '../types';
 
 interface CategoryCardProps {
-  name: string;
+  category: CategorySummary;
 }
 
-export default function CategoryCard({ name }: CategoryCardProps) {
+export default function CategoryCard({ category }: CategoryCardProps) {
+  const { name, description, icon, itemCount, unrankedCount, topItem } = category;
+
   return (
     <Link
       to={`/category/${encodeURIComponent(name)}`}
@@ -14,12 +17,22 @@ export default function CategoryCard({ name }: CategoryCardProps) {
       <div className='flex items-center justify-between'>
         <div className='flex items-center gap-4'>
           <div className='rounded-xl bg-indigo-50 p-3 text-indigo-600 transition-colors duration-300 group-hover:bg-indigo-600 group-hover:text-white'>
-            <Folder className='h-6 w-6' />
+            {icon ? (
+              <span className='flex h-6 w-6 items-center justify-center text-xl'>{icon}</span>
+            ) : (
+              <Folder className='h-6 w-6' />
+            )}
           </div>
           <div>
             <h3 className='font-semibold text-gray-900 transition-colors group-hover:text-indigo-700'>
               {name}
             </h3>
+            {description && <p className='text-sm text-gray-500'>{description}</p>}
+            <p className='text-sm text-gray-500'>
+              {itemCount} {itemCount === 1 ? 'item' : 'items'}
+              {unrankedCount > 0 && ` · ${unrankedCount} unranked`}
+            </p>
+            {topItem && <p className='text-sm text-gray-400'>Top: {topItem.name}</p>}
           </div>
         </div>
 
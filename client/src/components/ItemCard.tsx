@@ -13,7 +13,7 @@ export default function ItemCard({ item, onClick, rank }: ItemCardProps) {
     <>
       {item.imageUrl ? (
         <img
-          src={item.imageUrl}
+          src={item.imageVariants?.thumbnail ?? item.imageUrl}
           alt={item.name}
           className='h-20 w-20 rounded-xl bg-gray-100 object-contain shadow-inner'
         />
@@ -1,6 +1,7 @@
 import { Outlet, useLocation, Link, useNavigate } from 'react-router-dom';
 import { LogOut, ArrowLeft } from 'lucide-react';
 import FloatingActionButton from './FloatingActionButton';
+import { api } from '../api/client';
 
 export default function Layout() {
   const location = useLocation();
@@ -31,7 +32,7 @@ export default function Layout() {
           </div>
 
           <button
-            onClick={() => navigate('/login')} // Mock logout
+            onClick={() => api.logout().finally(() => navigate('/login'))}
             className='rounded-full p-2 text-gray-500 transition-all duration-300 hover:bg-red-50 hover:text-red-600'
             title='Logout'
           >
@@ -1,6 +1,6 @@
 import { useState, useEffect } from 'react';
 import { api } from '../api/client';
-import { Item } from '../types';
+import { Item, RankSession } from '../types';
 
 interface RankingFlowProps {
   targetItem: Item;
@@ -9,37 +9,16 @@ interface RankingFlowProps {
 }
 
 export default function RankingFlow({ targetItem, onComplete, onCancel }: RankingFlowProps) {
-  const [sortedItems, setSortedItems] = useState<Item[]>([]);
-  const [comparisonItem, setComparisonItem] = useState<Item | null>(null);
-  const [minIdx, setMinIdx] = useState(0);
-  const [maxIdx, setMaxIdx] = useState(0);
+  const [session, setSession] = useState<RankSession | null>(null);
   const [loading, setLoading] = useState(true);
 
+  // The server owns the binary search; an unfinished session for this item
+  // is resumed automatically when we start again.
   useEffect(() => {
     const init = async () => {
       setLoading(true);
       try {
-        const itemsInCategory = await api.getItemsByCategory(targetItem.category);
-        // Exclude target and unranked items, then sort by rank order (DESC)
-        const others = itemsInCategory
-          .filter(
-            (i) => i.id !== targetItem.id && i.rankOrder !== undefined && i.rankOrder !== null,
-          )
-          .sort((a, b) => b.rankOrder! - a.rankOrder!);
-
-        setSortedItems(others);
-
-        if (others.length === 0) {
-          finishRanking(100);
-          return;
-        }
-
-        setMinIdx(0);
-        setMaxIdx(others.length);
-
-        // Start binary search at the middle
-        const mid = Math.floor(others.length / 2);
-        setComparisonItem(others[mid]);
+        handleSession(await api.startRankSession(targetItem.id));
       } catch (e) {
         console.error(e);
       } finally {
@@ -50,63 +29,27 @@ export default function RankingFlow({ targetItem, onComplete, onCancel }: Rankin
     // eslint-disable-next-line react-hooks/exhaustive-deps
   }, [targetItem.id]);
 
-  const finishRanking = async (rank: number) => {
-    try {
-      await api.updateItemRank(targetItem.id, rank);
+  const handleSession = (next: RankSession) => {
+    setSession(next);
+    if (next.complete) {
       onComplete();
-    } catch (e) {
-      console.error(e);
-      alert('Failed to save rank');
     }
   };
 
-  const handleChoice = (preferTarget: boolean) => {
-    if (!comparisonItem) return;
-
-    let newMin = minIdx;
-    let newMax = maxIdx;
-
-    // Current list is sorted High -> Low (Best -> Worst)
-    // If Target is BETTER than Comparison (High -> Low):
-    // Target belongs to a lower index (closer to 0).
-    if (preferTarget) {
-      // Target > Comparison
-      // Search in left half [min, mid]
-      newMax = Math.floor((minIdx + maxIdx) / 2);
-    } else {
-      // Target < Comparison
-      // Search in right half [mid + 1, max]
-      newMin = Math.floor((minIdx + maxIdx) / 2) + 1;
-    }
+  const handleChoice = async (preferTarget: boolean) => {
+    if (!session) return;
 
-    if (newMin >= newMax) {
-      calculateAndSaveRank(newMin);
-    } else {
-      setMinIdx(newMin);
-      setMaxIdx(newMax);
-      const mid = Math.floor((newMin + newMax) / 2);
-      setComparisonItem(sortedItems[mid]);
+    try {
+      handleSession(
+        await api.answerRankSession(session.id, preferTarget ? 'target' : 'comparison'),
+      );
+    } catch (e) {
+      console.error(e);
+      alert('Failed to save rank');
     }
   };
 
-  const calculateAndSaveRank = async (insertIndex: number) => {
-    let newRank = 0;
-    if (sortedItems.length === 0) {
-      newRank = 1000;
-    } else if (insertIndex === 0) {
-      // Top of list
-      newRank = (sortedItems[0].rankOrder || 0) + 100;
-    } else if (insertIndex >= sortedItems.length) {
-      // Bottom of list
-      newRank = (sortedItems[sortedItems.length - 1].rankOrder || 0) - 100;
-    } else {
-      // Between two items
-      const above = sortedItems[insertIndex - 1].rankOrder || 0;
-      const below = sortedItems[insertIndex].rankOrder || 0;
-      newRank = (above + below) / 2;
-    }
-    await finishRanking(newRank);
-  };
+  const comparisonItem = session?.comparison;
 
   // Internal Card Component for consistency and isolation from the list-view ItemCard
   const RankingCard = ({
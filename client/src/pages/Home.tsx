@@ -2,9 +2,10 @@ import { useState, useEffect } from 'react';
 import { api } from '../api/client';
 import CategoryCard from '../components/CategoryCard';
 import { Loader2 } from 'lucide-react';
+import { CategorySummary } from '../types';
 
 export default function Home() {
-  const [categories, setCategories] = useState<string[]>([]);
+  const [categories, setCategories] = useState<CategorySummary[]>([]);
   const [loading, setLoading] = useState(true);
 
   useEffect(() => {
@@ -39,7 +40,7 @@ export default function Home() {
       ) : (
         <div className='grid grid-cols-1 gap-4 sm:grid-cols-2'>
           {categories.map((cat) => (
-            <CategoryCard key={cat} name={cat} />
+            <CategoryCard key={cat.id} category={cat} />
           ))}
         </div>
       )}
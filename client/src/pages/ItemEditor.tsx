@@ -36,7 +36,7 @@ export default function ItemEditor() {
     async function init() {
       // TODO: Share categories that we already fetched
       const cats = await api.getAllCategories();
-      setCategories(cats);
+      setCategories(cats.map((c) => c.name));
 
       if (!isNew && id) {
         const item = await api.getItem(id);
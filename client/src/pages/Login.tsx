@@ -4,6 +4,9 @@ import { useLocation, useNavigate } from 'react-router-dom';
 export default function Login() {
   const [username, setUsername] = useState('');
   const [password, setPassword] = useState('');
+  // Shown once the server asks for a second factor
+  const [needsCode, setNeedsCode] = useState(false);
+  const [code, setCode] = useState('');
   const [error, setError] = useState('');
   const navigate = useNavigate();
   const location = useLocation();
@@ -20,18 +23,33 @@ export default function Login() {
         headers: {
           'Content-Type': 'application/json',
         },
-        body: JSON.stringify({ username, password }),
+        body: JSON.stringify({
+          username,
+          password,
+          // Recovery codes look like "abcde-12345"; TOTP codes are digits
+          ...(needsCode && code
+            ? code.includes('-')
+              ? { recovery_code: code }
+              : { totp_code: code }
+            : {}),
+        }),
       });
 
       if (!response.ok) {
-        throw new Error('Invalid credentials');
+        const message = await response.text();
+        if (message === 'Two-factor code required') {
+          setNeedsCode(true);
+          return;
+        }
+        throw new Error(message || 'Invalid credentials');
       }
 
       const data = await response.json();
       localStorage.setItem('token', data.token);
+      localStorage.setItem('refreshToken', data.refresh_token);
       navigate(from, { replace: true });
     } catch (err) {
-      setError('Invalid username or password');
+      setError(needsCode ? 'Invalid username, password or code' : 'Invalid username or password');
     }
   };
 
@@ -79,6 +97,26 @@ export default function Login() {
               autoComplete='current-password'
             />
           </div>
+          {needsCode && (
+            <div className='mb-6'>
+              <label
+                className='mb-2 block text-sm font-bold text-gray-700 dark:text-gray-300'
+                htmlFor='code'
+              >
+                Authentication Code
+              </label>
+              <input
+                className='focus:shadow-outline w-full appearance-none rounded border px-3 py-2 leading-tight text-gray-700 shadow focus:outline-none dark:border-gray-600 dark:bg-gray-700 dark:text-gray-200'
+                id='code'
+                type='text'
+                placeholder='123456 or a recovery code'
+                value={code}
+                onChange={(e) => setCode(e.target.value)}
+                autoComplete='one-time-code'
+                autoFocus
+              />
+            </div>
+          )}
           <div className='flex items-center justify-between'>
             <button
               className='focus:shadow-outline w-full rounded bg-blue-500 px-4 py-2 font-bold text-white hover:bg-blue-700 focus:outline-none'
@@ -1,12 +1,50 @@
 export interface Item {
   id: string;
+  categoryId: string;
   category: string;
   name: string;
   notes?: string;
   imageUrl?: string;
+  imageVariants?: ImageVariants;
   createdAt: Date;
   rankOrder?: number;
   normalizedScore?: number;
 }
 
-export type CreateItemDTO = Omit<Item, 'id' | 'createdAt'>;
+// Resized copies of an uploaded image
+export interface ImageVariants {
+  thumbnail: string;
+  card: string;
+  full: string;
+}
+
+export interface CategorySummary {
+  id: string;
+  name: string;
+  description?: string;
+  icon?: string;
+  coverImageUrl?: string;
+  position?: number;
+  isPublic: boolean;
+  shareSlug?: string;
+  createdAt: Date;
+  updatedAt: Date;
+  itemCount: number;
+  rankedCount: number;
+  unrankedCount: number;
+  topItem?: Item;
+  role: CategoryRole;
+}
+
+export type CategoryRole = 'owner' | 'editor' | 'viewer';
+
+export type CreateItemDTO = Omit<Item, 'id' | 'categoryId' | 'createdAt'> & { categoryId?: string };
+
+export type RankChoice = 'target' | 'comparison';
+
+export interface RankSession {
+  id: string;
+  item: Item;
+  comparison?: Item;
+  complete: boolean;
+}
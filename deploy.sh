@@ -84,6 +84,7 @@ ssh -i $SSH_KEY $VM_USER@$VM_IP "sudo systemctl stop stuff-tracker || true"
 
 echo "  -> Backend Binary..."
 scp -i $SSH_KEY server/target-linux/release/server $VM_USER@$VM_IP:$APP_DIR/server
+scp -i $SSH_KEY server/target-linux/release/stuff-admin $VM_USER@$VM_IP:$APP_DIR/stuff-admin
 
 # Upload Configs
 echo "  -> Configs..."
@@ -94,6 +95,12 @@ scp -i $SSH_KEY nginx.conf $VM_USER@$VM_IP:/tmp/nginx.conf
 
 echo "[5/4] Configuring Remote Server..."
 ssh -i $SSH_KEY $VM_USER@$VM_IP << EOF
+    # 0. Generate a JWT signing key on first deploy; the server won't start without one
+    if [ ! -f $APP_DIR/.env ]; then
+        echo "JWT_SECRET=\$(openssl rand -hex 32)" > $APP_DIR/.env
+        chmod 600 $APP_DIR/.env
+    fi
+
     # 1. Setup Backend Service
     sudo mv /tmp/stuff-tracker.service /etc/systemd/system/stuff-tracker.service
     sudo chmod 644 /etc/systemd/system/stuff-tracker.service
@@ -110,12 +117,6 @@ ssh -i $SSH_KEY $VM_USER@$VM_IP << EOF
         sudo apt-get update && sudo apt-get install -y nginx
     fi
 
-    # Ensure sqlite3 is installed for user management
-    if ! command -v sqlite3 &> /dev/null; then
-        echo "Installing Sqlite3..."
-        sudo apt-get update && sudo apt-get install -y sqlite3
-    fi
-
     sudo mv /tmp/nginx.conf /etc/nginx/sites-available/stuff-tracker
     # Enable site by linking to sites-enabled
     sudo ln -sf /etc/nginx/sites-available/stuff-tracker /etc/nginx/sites-enabled/
@@ -0,0 +1,14 @@
+-- In-progress binary-insertion rankings, one per item.
+-- upper_rank / lower_rank narrow the window of ranks the item can land in:
+-- upper_rank is the lowest-ranked item known to beat it, lower_rank the
+-- highest-ranked item it is known to beat. NULL means unbounded.
+CREATE TABLE rank_sessions (
+    id TEXT PRIMARY KEY NOT NULL,
+    item_id TEXT NOT NULL UNIQUE,
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    upper_rank REAL,
+    lower_rank REAL,
+    comparison_item_id TEXT,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (item_id) REFERENCES items(id)
+);
@@ -0,0 +1,12 @@
+-- Every "I prefer X over Y" answer given while ranking
+CREATE TABLE comparisons (
+    id TEXT PRIMARY KEY NOT NULL,
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    category_id TEXT NOT NULL REFERENCES categories(id),
+    winner_id TEXT NOT NULL REFERENCES items(id),
+    loser_id TEXT NOT NULL REFERENCES items(id),
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_comparisons_winner ON comparisons(winner_id);
+CREATE INDEX idx_comparisons_loser ON comparisons(loser_id);
@@ -0,0 +1,3 @@
+-- How normalized_score is derived for items in the category:
+-- 'rank' (from rank_order) or 'bradley_terry' (fitted from comparisons)
+ALTER TABLE categories ADD COLUMN scoring_mode TEXT NOT NULL DEFAULT 'rank';
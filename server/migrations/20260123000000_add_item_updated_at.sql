@@ -0,0 +1,4 @@
+-- Last time an item was edited or re-ranked.
+-- SQLite can't add a column with a non-constant default, so it is set explicitly on write.
+ALTER TABLE items ADD COLUMN updated_at DATETIME;
+UPDATE items SET updated_at = created_at;
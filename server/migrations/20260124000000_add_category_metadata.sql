@@ -0,0 +1,6 @@
+-- Optional presentation details for categories.
+-- position is the user's manual sort order; unpositioned categories sort by name after it.
+ALTER TABLE categories ADD COLUMN description TEXT;
+ALTER TABLE categories ADD COLUMN icon TEXT;
+ALTER TABLE categories ADD COLUMN cover_image_url TEXT;
+ALTER TABLE categories ADD COLUMN position INTEGER;
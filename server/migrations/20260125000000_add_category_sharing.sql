@@ -0,0 +1,5 @@
+-- Public categories can be read without an account through their share_slug.
+-- The slug only exists while the category is public.
+ALTER TABLE categories ADD COLUMN is_public BOOLEAN NOT NULL DEFAULT 0;
+ALTER TABLE categories ADD COLUMN share_slug TEXT;
+CREATE UNIQUE INDEX idx_categories_share_slug ON categories(share_slug);
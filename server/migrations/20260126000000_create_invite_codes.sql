@@ -0,0 +1,7 @@
+-- Single-use codes that allow someone to register an account
+CREATE TABLE invite_codes (
+    code TEXT PRIMARY KEY NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    used_by INTEGER REFERENCES users(id),
+    used_at DATETIME
+);
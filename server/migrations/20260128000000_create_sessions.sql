@@ -0,0 +1,12 @@
+-- One row per logged-in device, holding the hash of its current refresh token
+CREATE TABLE sessions (
+    id TEXT PRIMARY KEY NOT NULL,
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    refresh_token_hash TEXT NOT NULL,
+    user_agent TEXT,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_used_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at DATETIME NOT NULL
+);
+
+CREATE INDEX idx_sessions_user_id ON sessions(user_id);
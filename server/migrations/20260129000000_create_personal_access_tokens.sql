@@ -0,0 +1,12 @@
+-- Long-lived tokens for scripts; only the hash of the token is stored
+CREATE TABLE personal_access_tokens (
+    id TEXT PRIMARY KEY NOT NULL,
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    name TEXT NOT NULL,
+    scope TEXT NOT NULL DEFAULT 'read',
+    token_hash TEXT NOT NULL UNIQUE,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_used_at DATETIME
+);
+
+CREATE INDEX idx_personal_access_tokens_user_id ON personal_access_tokens(user_id);
@@ -0,0 +1,7 @@
+-- Failed login attempts per username ('user:<name>') and per client IP ('ip:<addr>')
+CREATE TABLE login_throttle (
+    key TEXT PRIMARY KEY NOT NULL,
+    failures INTEGER NOT NULL DEFAULT 0,
+    last_failure_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    blocked_until DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
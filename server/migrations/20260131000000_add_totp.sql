@@ -0,0 +1,15 @@
+-- Optional TOTP second factor. The secret is set on enrollment and only
+-- required at login once a first code has confirmed it.
+ALTER TABLE users ADD COLUMN totp_secret TEXT;
+ALTER TABLE users ADD COLUMN totp_enabled BOOLEAN NOT NULL DEFAULT 0;
+-- Last accepted time step, so a code can't be replayed
+ALTER TABLE users ADD COLUMN totp_last_step INTEGER;
+
+CREATE TABLE recovery_codes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    code_hash TEXT NOT NULL,
+    used_at DATETIME
+);
+
+CREATE INDEX idx_recovery_codes_user_id ON recovery_codes(user_id);
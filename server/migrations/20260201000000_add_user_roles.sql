@@ -0,0 +1,6 @@
+ALTER TABLE users ADD COLUMN is_admin BOOLEAN NOT NULL DEFAULT 0;
+-- Disabled accounts can't log in; their data is kept
+ALTER TABLE users ADD COLUMN disabled_at DATETIME;
+
+-- Existing installs were set up with a single account by hand; it becomes the admin
+UPDATE users SET is_admin = 1 WHERE id = (SELECT MIN(id) FROM users);
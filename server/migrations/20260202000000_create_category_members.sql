@@ -0,0 +1,12 @@
+-- Users a category is shared with, besides its owner (categories.user_id)
+CREATE TABLE category_members (
+    category_id TEXT NOT NULL REFERENCES categories(id),
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    role TEXT NOT NULL DEFAULT 'viewer', -- 'editor' or 'viewer'
+    -- Where the member lists the category; categories.position is the owner's
+    position INTEGER,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (category_id, user_id)
+);
+
+CREATE INDEX idx_category_members_user_id ON category_members(user_id);
@@ -0,0 +1,23 @@
+-- Files saved by POST /api/upload. Items and category covers may only be
+-- given local image URLs their user uploaded.
+CREATE TABLE uploads (
+    url TEXT PRIMARY KEY NOT NULL, -- the full size image, e.g. /uploads/<uuid>.jpg
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    -- Of the saved full size file. NULL for uploads from before this table.
+    size INTEGER,
+    width INTEGER,
+    height INTEGER,
+    hash TEXT, -- SHA-256, hex
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_uploads_user_id ON uploads(user_id);
+
+-- Earlier uploads belong to whoever's item or category uses them
+INSERT OR IGNORE INTO uploads (url, user_id)
+SELECT image_url, user_id FROM items
+WHERE image_url LIKE '/uploads/%' AND user_id IS NOT NULL;
+
+INSERT OR IGNORE INTO uploads (url, user_id)
+SELECT cover_image_url, user_id FROM categories
+WHERE cover_image_url LIKE '/uploads/%' AND user_id IS NOT NULL;
@@ -0,0 +1,4 @@
+-- uploads.size now covers every file an upload wrote, its resized copies and
+-- other formats included. Sizes recorded so far were of the full size file
+-- alone, so they are forgotten for `stuff-admin upload-sizes` to measure again.
+UPDATE uploads SET size = NULL;
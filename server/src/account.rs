@@ -0,0 +1,285 @@
+use axum::{
+    Extension, Json,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+};
+use sqlx::{SqliteConnection, SqlitePool};
+use std::sync::Arc;
+
+use crate::auth::{
+    JwtKeys, ensure_username_available, map_username_conflict, replace_password, validate_username,
+    verify_password,
+};
+use crate::models::{
+    Account, ChangePassword, Claims, ConfirmPassword, LoginResponse, UpdateAccount, User,
+};
+use crate::sessions::{require_session, start_session, user_agent};
+use crate::upload::{delete_image, release_uploads};
+
+pub async fn get_account(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Account>, (StatusCode, String)> {
+    let account = sqlx::query_as!(
+        Account,
+        "SELECT id, username, totp_enabled, is_admin FROM users WHERE id = ?",
+        claims.uid
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, "Account not found".to_string()))?;
+
+    Ok(Json(account))
+}
+
+pub async fn update_account(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<UpdateAccount>,
+) -> Result<Json<Account>, (StatusCode, String)> {
+    require_session(&claims)?;
+
+    if let Some(username) = &payload.username {
+        validate_username(username)?;
+
+        let mut tx = pool
+            .begin()
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        ensure_username_available(&mut tx, username, Some(claims.uid)).await?;
+
+        sqlx::query!(
+            "UPDATE users SET username = ? WHERE id = ?",
+            username,
+            claims.uid
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(map_username_conflict)?;
+
+        tx.commit()
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+
+    get_account(State(pool), Extension(claims)).await
+}
+
+/// Changing the password signs out every session, including the caller's; the
+/// response carries tokens for a new one.
+pub async fn change_password(
+    State(pool): State<SqlitePool>,
+    State(keys): State<Arc<JwtKeys>>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Json(payload): Json<ChangePassword>,
+) -> Result<Json<LoginResponse>, (StatusCode, String)> {
+    require_session(&claims)?;
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(claims.uid)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Account not found".to_string()))?;
+
+    if !verify_password(&payload.current_password, &user.password_hash)? {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Current password is incorrect".to_string(),
+        ));
+    }
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    replace_password(&mut tx, claims.uid, &payload.new_password).await?;
+
+    let tokens = start_session(&mut tx, &keys, user.id, user_agent(&headers)).await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(tokens))
+}
+
+/// Deletes the caller's account and everything in it, images included.
+pub async fn delete_account(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<ConfirmPassword>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    require_session(&claims)?;
+
+    let user = sqlx::query!(
+        "SELECT password_hash, is_admin FROM users WHERE id = ?",
+        claims.uid
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, "Account not found".to_string()))?;
+
+    if !verify_password(&payload.current_password, &user.password_hash)? {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Current password is incorrect".to_string(),
+        ));
+    }
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // Someone has to be left to manage the other accounts
+    if user.is_admin {
+        let other_admins = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM users WHERE is_admin = 1 AND disabled_at IS NULL AND id != ?",
+            claims.uid
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        if other_admins == 0 {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Make another user an admin before deleting the last admin account".to_string(),
+            ));
+        }
+    }
+
+    let images = delete_user_data(&mut tx, claims.uid)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // Only once the rows are gone; a leftover file is harmless, a missing one isn't
+    for url in images {
+        let _ = delete_image(&url).await;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Removes a user and everything they own. Items and comparisons they added
+/// to categories shared with them stay behind and pass to the owner, as do
+/// uploads those items use. Returns the URLs of uploads no one uses any more,
+/// to be deleted once the transaction has committed.
+pub async fn delete_user_data(
+    conn: &mut SqliteConnection,
+    user_id: i64,
+) -> Result<Vec<String>, sqlx::Error> {
+    for statement in [
+        "UPDATE items SET user_id = (SELECT c.user_id FROM categories c WHERE c.id = items.category_id)
+         WHERE user_id = ? AND category_id NOT IN (SELECT id FROM categories WHERE user_id = ?)",
+        "UPDATE comparisons
+         SET user_id = (SELECT c.user_id FROM categories c WHERE c.id = comparisons.category_id)
+         WHERE user_id = ? AND category_id NOT IN (SELECT id FROM categories WHERE user_id = ?)",
+    ] {
+        sqlx::query(statement)
+            .bind(user_id)
+            .bind(user_id)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    let images = sqlx::query_scalar::<_, String>(
+        "SELECT i.image_url FROM items i
+         JOIN categories c ON i.category_id = c.id
+         WHERE c.user_id = ? AND i.image_url IS NOT NULL
+         UNION
+         SELECT cover_image_url FROM categories WHERE user_id = ? AND cover_image_url IS NOT NULL
+         UNION
+         SELECT url FROM uploads WHERE user_id = ?",
+    )
+    .bind(user_id)
+    .bind(user_id)
+    .bind(user_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    // Children before parents; foreign keys are enforced
+    sqlx::query(
+        "DELETE FROM comparisons WHERE user_id = ?
+            OR category_id IN (SELECT id FROM categories WHERE user_id = ?)
+            OR winner_id IN (SELECT i.id FROM items i
+                             JOIN categories c ON i.category_id = c.id WHERE c.user_id = ?)
+            OR loser_id IN (SELECT i.id FROM items i
+                            JOIN categories c ON i.category_id = c.id WHERE c.user_id = ?)",
+    )
+    .bind(user_id)
+    .bind(user_id)
+    .bind(user_id)
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        "DELETE FROM rank_sessions WHERE user_id = ?
+            OR item_id IN (SELECT i.id FROM items i
+                           JOIN categories c ON i.category_id = c.id WHERE c.user_id = ?)",
+    )
+    .bind(user_id)
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        "DELETE FROM category_members
+         WHERE user_id = ? OR category_id IN (SELECT id FROM categories WHERE user_id = ?)",
+    )
+    .bind(user_id)
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+
+    for statement in [
+        "DELETE FROM items WHERE category_id IN (SELECT id FROM categories WHERE user_id = ?)",
+        "DELETE FROM categories WHERE user_id = ?",
+    ] {
+        sqlx::query(statement)
+            .bind(user_id)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    // Imported copies can share image files with another account
+    let unused = release_uploads(&mut *conn, images).await?;
+
+    // The rest are still on items or covers someone else now has
+    sqlx::query(
+        "UPDATE uploads SET user_id = COALESCE(
+             (SELECT COALESCE(i.user_id, c.user_id) FROM items i
+              JOIN categories c ON i.category_id = c.id WHERE i.image_url = uploads.url),
+             (SELECT user_id FROM categories WHERE cover_image_url = uploads.url))
+         WHERE user_id = ?",
+    )
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+
+    for statement in [
+        "DELETE FROM sessions WHERE user_id = ?",
+        "DELETE FROM personal_access_tokens WHERE user_id = ?",
+        "DELETE FROM recovery_codes WHERE user_id = ?",
+        // A used invite can't be handed back out
+        "DELETE FROM invite_codes WHERE used_by = ?",
+        "DELETE FROM users WHERE id = ?",
+    ] {
+        sqlx::query(statement)
+            .bind(user_id)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(unused)
+}
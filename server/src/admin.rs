@@ -0,0 +1,213 @@
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+use crate::auth::{self, generate_invite_code, replace_password};
+use crate::models::{
+    AdminUser, Claims, CreateUser, InviteCode, ResetPassword, StorageUsage, UpdateUser, UserStorage,
+};
+use crate::upload::uploads_size;
+
+// Every route here sits behind `auth::require_admin`
+
+const SELECT_USERS: &str = "SELECT u.id, u.username, u.is_admin, u.disabled_at, u.totp_enabled,
+        (SELECT COUNT(*) FROM items i WHERE i.user_id = u.id) as item_count,
+        (SELECT COUNT(*) FROM categories c WHERE c.user_id = u.id) as category_count
+    FROM users u";
+
+async fn fetch_user(pool: &SqlitePool, id: i64) -> Result<AdminUser, (StatusCode, String)> {
+    sqlx::query_as::<_, AdminUser>(&format!("{SELECT_USERS} WHERE u.id = ?"))
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "User not found".to_string()))
+}
+
+pub async fn get_users(
+    State(pool): State<SqlitePool>,
+) -> Result<Json<Vec<AdminUser>>, (StatusCode, String)> {
+    let users = sqlx::query_as::<_, AdminUser>(&format!("{SELECT_USERS} ORDER BY u.id"))
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(users))
+}
+
+pub async fn create_user(
+    State(pool): State<SqlitePool>,
+    Json(payload): Json<CreateUser>,
+) -> Result<Json<AdminUser>, (StatusCode, String)> {
+    let user_id = auth::create_user(
+        &pool,
+        &payload.username,
+        &payload.password,
+        payload.is_admin,
+    )
+    .await?;
+
+    Ok(Json(fetch_user(&pool, user_id).await?))
+}
+
+/// Disabling an account signs it out everywhere; its data is kept.
+pub async fn update_user(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i64>,
+    Json(payload): Json<UpdateUser>,
+) -> Result<Json<AdminUser>, (StatusCode, String)> {
+    // Stops the last admin from locking everyone out
+    if id == claims.uid && (payload.is_admin == Some(false) || payload.disabled == Some(true)) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "You can't demote or disable your own account".to_string(),
+        ));
+    }
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let exists = sqlx::query_scalar!("SELECT id FROM users WHERE id = ?", id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if exists.is_none() {
+        return Err((StatusCode::NOT_FOUND, "User not found".to_string()));
+    }
+
+    if let Some(is_admin) = payload.is_admin {
+        sqlx::query!("UPDATE users SET is_admin = ? WHERE id = ?", is_admin, id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+
+    match payload.disabled {
+        Some(true) => {
+            sqlx::query!(
+                "UPDATE users SET disabled_at = COALESCE(disabled_at, CURRENT_TIMESTAMP) WHERE id = ?",
+                id
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+            sqlx::query!("DELETE FROM sessions WHERE user_id = ?", id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        }
+        Some(false) => {
+            sqlx::query!("UPDATE users SET disabled_at = NULL WHERE id = ?", id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        }
+        None => {}
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(fetch_user(&pool, id).await?))
+}
+
+/// Sets a new password and signs the user out of every session.
+pub async fn reset_password(
+    State(pool): State<SqlitePool>,
+    Path(id): Path<i64>,
+    Json(payload): Json<ResetPassword>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if !replace_password(&mut tx, id, &payload.new_password).await? {
+        return Err((StatusCode::NOT_FOUND, "User not found".to_string()));
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn get_invites(
+    State(pool): State<SqlitePool>,
+) -> Result<Json<Vec<InviteCode>>, (StatusCode, String)> {
+    let invites = sqlx::query_as::<_, InviteCode>(
+        "SELECT code, created_at FROM invite_codes WHERE used_by IS NULL ORDER BY created_at",
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(invites))
+}
+
+pub async fn create_invite(
+    State(pool): State<SqlitePool>,
+) -> Result<Json<InviteCode>, (StatusCode, String)> {
+    let code = generate_invite_code();
+
+    let invite = sqlx::query_as::<_, InviteCode>(
+        "INSERT INTO invite_codes (code) VALUES (?) RETURNING code, created_at",
+    )
+    .bind(&code)
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(invite))
+}
+
+/// Image usage per user, from the sizes recorded for their uploads.
+pub async fn get_storage(
+    State(pool): State<SqlitePool>,
+) -> Result<Json<StorageUsage>, (StatusCode, String)> {
+    let users = sqlx::query_as::<_, (i64, String)>("SELECT id, username FROM users ORDER BY id")
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // Uploads from before sizes were recorded count as 0 bytes until
+    // `stuff-admin upload-sizes` measures them
+    let usage: HashMap<i64, (u64, u64)> = sqlx::query_as::<_, (i64, i64, i64)>(
+        "SELECT user_id, COUNT(*), COALESCE(SUM(size), 0) FROM uploads GROUP BY user_id",
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .into_iter()
+    .map(|(user_id, count, bytes)| (user_id, (count as u64, bytes as u64)))
+    .collect();
+
+    let total_bytes = uploads_size()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let users = users
+        .into_iter()
+        .map(|(id, username)| {
+            let (image_count, bytes) = usage.get(&id).copied().unwrap_or_default();
+            UserStorage {
+                id,
+                username,
+                image_count,
+                bytes,
+            }
+        })
+        .collect();
+
+    Ok(Json(StorageUsage { total_bytes, users }))
+}
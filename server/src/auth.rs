@@ -1,11 +1,11 @@
 use argon2::{
     Argon2,
-    password_hash::{PasswordHash, PasswordVerifier},
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
 };
 use axum::{
-    Json, RequestPartsExt,
-    extract::State,
-    http::{Request, StatusCode},
+    Extension, Json, RequestPartsExt,
+    extract::{ConnectInfo, State},
+    http::{HeaderMap, Request, StatusCode},
     middleware::Next,
     response::Response,
 };
@@ -13,69 +13,529 @@ use axum_extra::{
     TypedHeader,
     headers::{Authorization, authorization::Bearer},
 };
-use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
-use sqlx::SqlitePool;
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, decode_header, encode};
+use rand::{Rng, distr::Alphanumeric};
+use sha2::{Digest, Sha256};
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, LazyLock};
 
-use crate::models::{Claims, LoginRequest, LoginResponse, User};
+use crate::models::{Claims, LoginRequest, LoginResponse, RegisterRequest, User};
+use crate::sessions::{start_session, user_agent};
+use crate::throttle;
+use crate::tokens::{ACCESS_TOKEN_PREFIX, access_token_claims};
+use crate::totp::verify_second_factor;
 
-// Secret key for JWT signing. In production, this should come from env.
-// For simplicity in this plan, we'll read it from env in main and pass it or use a lazy_static/const if acceptable.
-// Better: Store in AppState. But here we'll use a simple env var read or functionality.
-fn get_jwt_secret() -> String {
-    std::env::var("JWT_SECRET").unwrap_or_else(|_| "secret".to_string())
+const MIN_USERNAME_LEN: usize = 3;
+const MAX_USERNAME_LEN: usize = 32;
+const MIN_PASSWORD_LEN: usize = 8;
+const INVITE_CODE_LEN: usize = 16;
+// Access tokens are short-lived; clients renew them with their refresh token
+const ACCESS_TOKEN_MINUTES: i64 = 15;
+
+static DUMMY_PASSWORD_HASH: LazyLock<String> =
+    LazyLock::new(|| hash_password(&random_token(MIN_PASSWORD_LEN)).expect("valid hash"));
+
+// HS256 secrets shorter than the hash output are easier to brute force
+const MIN_JWT_SECRET_LEN: usize = 32;
+// Key id used when only JWT_SECRET is configured
+const DEFAULT_KID: &str = "default";
+
+/// Keys for signing and verifying access tokens. Tokens are signed with the
+/// first key and name it in their `kid` header; the others still verify, so
+/// a new key can be rolled out without logging everyone out.
+pub struct JwtKeys {
+    signing_kid: String,
+    signing_key: EncodingKey,
+    verification_keys: HashMap<String, DecodingKey>,
+}
+
+impl JwtKeys {
+    /// `keys` are `(kid, secret)` pairs, the signing key first.
+    pub fn new(keys: &[(&str, &str)]) -> Result<Self, String> {
+        let (signing_kid, signing_secret) = keys.first().ok_or("No JWT keys configured")?;
+
+        let mut verification_keys = HashMap::new();
+        for (kid, secret) in keys {
+            if kid.is_empty() || secret.is_empty() {
+                return Err("JWT key ids and secrets can't be empty".to_string());
+            }
+            if verification_keys
+                .insert(kid.to_string(), DecodingKey::from_secret(secret.as_bytes()))
+                .is_some()
+            {
+                return Err(format!("Duplicate JWT key id '{}'", kid));
+            }
+        }
+
+        Ok(Self {
+            signing_kid: signing_kid.to_string(),
+            signing_key: EncodingKey::from_secret(signing_secret.as_bytes()),
+            verification_keys,
+        })
+    }
+
+    /// A throwaway key, so tokens don't survive a restart.
+    pub fn random() -> Self {
+        let secret = random_token(2 * MIN_JWT_SECRET_LEN);
+        Self::new(&[(DEFAULT_KID, &secret)]).expect("valid key")
+    }
+
+    /// Loads keys from `JWT_KEYS` (`kid:secret` pairs separated by commas,
+    /// the signing key first) or else `JWT_SECRET`. Unless `APP_ENV` is
+    /// `development`, a missing or short secret is an error.
+    pub fn from_env() -> Result<Self, String> {
+        let development = std::env::var("APP_ENV").is_ok_and(|env| env == "development");
+        Self::from_config(
+            std::env::var("JWT_KEYS").ok().as_deref(),
+            std::env::var("JWT_SECRET").ok().as_deref(),
+            development,
+        )
+    }
+
+    pub fn from_config(
+        jwt_keys: Option<&str>,
+        jwt_secret: Option<&str>,
+        development: bool,
+    ) -> Result<Self, String> {
+        let keys: Vec<(&str, &str)> = match (jwt_keys, jwt_secret) {
+            (Some(list), _) => list
+                .split(',')
+                .map(|entry| {
+                    entry
+                        .trim()
+                        .split_once(':')
+                        .ok_or_else(|| "JWT_KEYS entries must look like kid:secret".to_string())
+                })
+                .collect::<Result<_, _>>()?,
+            (None, Some(secret)) => vec![(DEFAULT_KID, secret)],
+            (None, None) if development => {
+                eprintln!("JWT_SECRET is not set; using a random key for this run");
+                return Ok(Self::random());
+            }
+            (None, None) => {
+                return Err(
+                    "JWT_SECRET or JWT_KEYS must be set (or APP_ENV=development)".to_string(),
+                );
+            }
+        };
+
+        if !development
+            && let Some((kid, _)) = keys
+                .iter()
+                .find(|(_, secret)| secret.len() < MIN_JWT_SECRET_LEN)
+        {
+            return Err(format!(
+                "JWT key '{}' must be at least {} characters",
+                kid, MIN_JWT_SECRET_LEN
+            ));
+        }
+
+        Self::new(&keys)
+    }
+
+    fn encode(&self, claims: &Claims) -> jsonwebtoken::errors::Result<String> {
+        let header = Header {
+            kid: Some(self.signing_kid.clone()),
+            ..Header::default()
+        };
+        encode(&header, claims, &self.signing_key)
+    }
+
+    fn decode(&self, token: &str) -> Option<Claims> {
+        let kid = decode_header(token).ok()?.kid?;
+        let key = self.verification_keys.get(&kid)?;
+        decode::<Claims>(token, key, &Validation::default())
+            .ok()
+            .map(|data| data.claims)
+    }
 }
 
 pub async fn login(
     State(pool): State<SqlitePool>,
+    State(keys): State<Arc<JwtKeys>>,
+    peer: Option<Extension<ConnectInfo<SocketAddr>>>,
+    headers: HeaderMap,
     Json(payload): Json<LoginRequest>,
 ) -> Result<Json<LoginResponse>, (StatusCode, String)> {
-    // 1. Find user
+    let username_key = throttle::username_key(&payload.username);
+    let ip_key = throttle::ip_key(&throttle::client_ip(
+        &headers,
+        peer.map(|Extension(ConnectInfo(addr))| addr),
+    ));
+
+    // 1. Refuse outright while the username or address is backing off
+    if let Some(secs) = throttle::retry_after(&pool, &[&username_key, &ip_key])
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    {
+        return Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            format!("Too many failed attempts, try again in {} seconds", secs),
+        ));
+    }
+
+    // 2. Find user
     let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE username = ?")
         .bind(&payload.username)
         .fetch_optional(&pool)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    let user = user.ok_or((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()))?;
+    // 3. Verify password. Unknown users are checked against a dummy hash so
+    // they take as long as known ones.
+    let password_hash = user
+        .as_ref()
+        .map_or(DUMMY_PASSWORD_HASH.as_str(), |u| u.password_hash.as_str());
+    let verified = verify_password(&payload.password, password_hash)?;
 
-    // 2. Verify password
-    let parsed_hash = PasswordHash::new(&user.password_hash).map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Invalid hash format".to_string(),
-        )
-    })?;
+    let Some(user) = user.filter(|_| verified) else {
+        for (key, policy) in [
+            (&username_key, &throttle::USERNAME_POLICY),
+            (&ip_key, &throttle::IP_POLICY),
+        ] {
+            throttle::record_failure(&pool, key, policy)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        }
+        return Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()));
+    };
 
-    if Argon2::default()
-        .verify_password(payload.password.as_bytes(), &parsed_hash)
-        .is_err()
+    // 4. With two-factor authentication on, a code is needed as well
+    if !verify_second_factor(
+        &pool,
+        &user,
+        payload.totp_code.as_deref(),
+        payload.recovery_code.as_deref(),
+    )
+    .await?
     {
-        return Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()));
+        let message = if payload.totp_code.is_none() && payload.recovery_code.is_none() {
+            "Two-factor code required"
+        } else {
+            throttle::record_failure(&pool, &username_key, &throttle::USERNAME_POLICY)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            "Invalid two-factor code"
+        };
+        return Err((StatusCode::UNAUTHORIZED, message.to_string()));
+    }
+
+    if user.disabled_at.is_some() {
+        return Err((StatusCode::FORBIDDEN, "Account disabled".to_string()));
     }
 
-    // 3. Generate JWT
+    throttle::clear(&pool, &username_key)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // 5. Open a session and generate its tokens
+    let mut conn = pool
+        .acquire()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let tokens = start_session(&mut conn, &keys, user.id, user_agent(&headers)).await?;
+
+    Ok(Json(tokens))
+}
+
+pub(crate) fn issue_token(
+    keys: &JwtKeys,
+    username: String,
+    user_id: i64,
+    token_version: i64,
+    is_admin: bool,
+    session_id: String,
+) -> jsonwebtoken::errors::Result<String> {
     let expiration = chrono::Utc::now()
-        .checked_add_signed(chrono::Duration::hours(24))
+        .checked_add_signed(chrono::Duration::minutes(ACCESS_TOKEN_MINUTES))
         .expect("valid timestamp")
         .timestamp();
 
     let claims = Claims {
-        sub: user.username,
-        uid: user.id,
+        sub: username,
+        uid: user_id,
         exp: expiration as usize,
+        ver: token_version,
+        sid: Some(session_id),
+        admin: is_admin,
     };
 
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(get_jwt_secret().as_bytes()),
+    keys.encode(&claims)
+}
+
+/// Argon2 hash in PHC string format, as stored in `users.password_hash`.
+pub fn hash_password(password: &str) -> Result<String, argon2::password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+}
+
+pub(crate) fn verify_password(
+    password: &str,
+    password_hash: &str,
+) -> Result<bool, (StatusCode, String)> {
+    let parsed_hash = PasswordHash::new(password_hash).map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Invalid hash format".to_string(),
+        )
+    })?;
+
+    Ok(Argon2::default()
+        .verify_password(password.as_bytes(), &parsed_hash)
+        .is_ok())
+}
+
+pub(crate) fn random_token(len: usize) -> String {
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+// Generated tokens are random enough that a plain hash is as good as a slow one
+pub(crate) fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+pub fn generate_invite_code() -> String {
+    random_token(INVITE_CODE_LEN)
+}
+
+pub(crate) fn validate_username(username: &str) -> Result<(), (StatusCode, String)> {
+    let len = username.chars().count();
+    if !(MIN_USERNAME_LEN..=MAX_USERNAME_LEN).contains(&len) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Username must be between {} and {} characters",
+                MIN_USERNAME_LEN, MAX_USERNAME_LEN
+            ),
+        ));
+    }
+
+    if !username
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Username may only contain letters, numbers, '_', '-' and '.'".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn validate_password(password: &str) -> Result<(), (StatusCode, String)> {
+    if password.chars().count() < MIN_PASSWORD_LEN {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Password must be at least {} characters", MIN_PASSWORD_LEN),
+        ));
+    }
+    Ok(())
+}
+
+/// Usernames are unique regardless of case so "Alice" can't shadow "alice".
+/// `except` is the user being renamed, who may change the case of their own name.
+pub(crate) async fn ensure_username_available(
+    conn: &mut SqliteConnection,
+    username: &str,
+    except: Option<i64>,
+) -> Result<(), (StatusCode, String)> {
+    let taken = sqlx::query!(
+        "SELECT id FROM users WHERE username = ? COLLATE NOCASE AND id IS NOT ?",
+        username,
+        except
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    match taken {
+        Some(_) => Err((StatusCode::CONFLICT, "Username already taken".to_string())),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn map_username_conflict(e: sqlx::Error) -> (StatusCode, String) {
+    match e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => {
+            (StatusCode::CONFLICT, "Username already taken".to_string())
+        }
+        e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// Validates and stores a new account, for admins; self-service signups go
+/// through `register` and need an invite.
+pub async fn create_user(
+    pool: &SqlitePool,
+    username: &str,
+    password: &str,
+    is_admin: bool,
+) -> Result<i64, (StatusCode, String)> {
+    validate_username(username)?;
+    validate_password(password)?;
+
+    let password_hash =
+        hash_password(password).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    ensure_username_available(&mut tx, username, None).await?;
+
+    let user_id = sqlx::query_scalar!(
+        "INSERT INTO users (username, password_hash, is_admin) VALUES (?, ?, ?) RETURNING id",
+        username,
+        password_hash,
+        is_admin
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_username_conflict)?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(user_id)
+}
+
+/// Stores a new password and signs the user out of every session. Returns
+/// false if there is no such user.
+pub async fn replace_password(
+    conn: &mut SqliteConnection,
+    user_id: i64,
+    password: &str,
+) -> Result<bool, (StatusCode, String)> {
+    validate_password(password)?;
+
+    let password_hash =
+        hash_password(password).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let result = sqlx::query!(
+        "UPDATE users SET password_hash = ?, token_version = token_version + 1 WHERE id = ?",
+        password_hash,
+        user_id
     )
+    .execute(&mut *conn)
+    .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    Ok(Json(LoginResponse { token }))
+    sqlx::query!("DELETE FROM sessions WHERE user_id = ?", user_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn register(
+    State(pool): State<SqlitePool>,
+    State(keys): State<Arc<JwtKeys>>,
+    headers: HeaderMap,
+    Json(payload): Json<RegisterRequest>,
+) -> Result<Json<LoginResponse>, (StatusCode, String)> {
+    validate_username(&payload.username)?;
+    validate_password(&payload.password)?;
+
+    let password_hash = hash_password(&payload.password)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // Writing first takes the database's write lock, so of two registrations
+    // racing with the same invite the second waits and then finds it used
+    let user_id = sqlx::query_scalar!(
+        "INSERT INTO users (username, password_hash) VALUES (?, ?) RETURNING id",
+        payload.username,
+        password_hash
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_username_conflict)?;
+
+    let claimed = sqlx::query!(
+        "UPDATE invite_codes SET used_by = ?, used_at = CURRENT_TIMESTAMP
+         WHERE code = ? AND used_by IS NULL",
+        user_id,
+        payload.invite_code
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if claimed.rows_affected() == 0 {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Invalid or already used invite code".to_string(),
+        ));
+    }
+
+    ensure_username_available(&mut tx, &payload.username, Some(user_id)).await?;
+
+    let tokens = start_session(&mut tx, &keys, user_id, user_agent(&headers)).await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(tokens))
+}
+
+async fn session_claims(
+    pool: &SqlitePool,
+    keys: &JwtKeys,
+    token: &str,
+) -> Result<Claims, StatusCode> {
+    // Validate the token
+    let claims = keys.decode(token).ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let session_id = claims.sid.as_deref().ok_or(StatusCode::UNAUTHORIZED)?;
+
+    // Tokens for a revoked or expired session, a disabled account, or issued
+    // before the last password change, are void
+    let user = sqlx::query!(
+        "SELECT u.token_version, u.is_admin
+         FROM sessions s
+         JOIN users u ON s.user_id = u.id
+         WHERE s.id = ? AND s.user_id = ? AND s.expires_at > CURRENT_TIMESTAMP
+           AND u.disabled_at IS NULL",
+        session_id,
+        claims.uid
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    if user.token_version != claims.ver {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    // A role change applies straight away, not when the token is next refreshed
+    Ok(Claims {
+        admin: user.is_admin,
+        ..claims
+    })
 }
 
 pub async fn auth_middleware(
+    State(pool): State<SqlitePool>,
+    State(keys): State<Arc<JwtKeys>>,
     request: Request<axum::body::Body>,
     next: Next,
 ) -> Result<Response, StatusCode> {
@@ -89,17 +549,29 @@ pub async fn auth_middleware(
 
     let token = auth_header.token();
 
-    // Validate the token
-    let token_data = decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(get_jwt_secret().as_bytes()),
-        &Validation::default(),
-    )
-    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+    // Personal access tokens are told apart from JWTs by their prefix
+    let claims = if token.starts_with(ACCESS_TOKEN_PREFIX) {
+        access_token_claims(&pool, token, &parts.method).await?
+    } else {
+        session_claims(&pool, &keys, token).await?
+    };
 
     // Store claims in request extensions if needed (e.g. for user context)
-    parts.extensions.insert(token_data.claims);
+    parts.extensions.insert(claims);
 
     let request = Request::from_parts(parts, body);
     Ok(next.run(request).await)
 }
+
+/// Layered on admin routes, inside `auth_middleware`. Admin work needs a real
+/// login, not a personal access token.
+pub async fn require_admin(
+    Extension(claims): Extension<Claims>,
+    request: Request<axum::body::Body>,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    if !claims.admin || claims.sid.is_none() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(next.run(request).await)
+}
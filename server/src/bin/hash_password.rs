@@ -1,22 +0,0 @@
-use argon2::{
-    Argon2,
-    password_hash::{PasswordHasher, SaltString, rand_core::OsRng},
-};
-use std::env;
-
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        eprintln!("Usage: cargo run --bin hash_password <password>");
-        std::process::exit(1);
-    }
-
-    let password = &args[1];
-    let salt = SaltString::generate(&mut OsRng);
-    let argon2 = Argon2::default();
-
-    match argon2.hash_password(password.as_bytes(), &salt) {
-        Ok(hash) => println!("{}", hash),
-        Err(e) => eprintln!("Error hashing password: {}", e),
-    }
-}
@@ -0,0 +1,282 @@
+use clap::{Parser, Subcommand};
+use server::{
+    account::delete_user_data,
+    auth::{create_user, generate_invite_code, replace_password},
+    maintenance::{self, Error},
+    models::Export,
+    upload::{UploadSettings, delete_image, regenerate_variants},
+};
+use sqlx::{
+    SqlitePool,
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+};
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+// Uploads this recent may belong to an item that hasn't been saved yet
+const ORPHAN_MIN_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Maintenance for a Stuff Tracker database. Connects to `DATABASE_URL`
+/// (default `sqlite:data.db`) and applies pending migrations first. Run it
+/// from the directory holding `uploads/`.
+#[derive(Parser)]
+#[command(name = "stuff-admin")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Only apply pending migrations
+    Migrate,
+    /// Manage accounts
+    #[command(subcommand)]
+    User(UserCommand),
+    /// Create a single-use invite code and print it
+    Invite,
+    /// List categories with their item counts
+    Categories {
+        /// Only this user's categories
+        #[arg(long)]
+        user: Option<String>,
+    },
+    /// Write a user's categories, items and comparisons as JSON
+    Export {
+        username: String,
+        /// File to write; defaults to stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Add categories from an export to a user's account
+    Import { username: String, file: PathBuf },
+    /// Look for broken references and missing images
+    Check,
+    /// Delete uploaded images nothing refers to
+    CleanupImages {
+        /// Only list what would be deleted
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Create the resized copies missing from older uploads
+    ImageVariants {
+        /// Re-encode every upload, e.g. after changing UPLOAD_FORMATS
+        #[arg(long)]
+        all: bool,
+    },
+    /// Record the disk usage of uploads saved before it was tracked
+    UploadSizes,
+}
+
+#[derive(Subcommand)]
+enum UserCommand {
+    /// List every account
+    List,
+    /// Create an account; the password is read from stdin
+    Create {
+        username: String,
+        #[arg(long)]
+        admin: bool,
+    },
+    /// Delete an account with all of its data and images
+    Delete {
+        username: String,
+        /// Required, as there's no undo
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Set a new password, read from stdin, and sign the user out everywhere
+    ResetPassword { username: String },
+}
+
+#[tokio::main]
+async fn main() {
+    dotenvy::dotenv().ok();
+
+    if let Err(e) = run(Cli::parse()).await {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), Error> {
+    let db_url = std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite:data.db".to_string());
+    let options = SqliteConnectOptions::from_str(&db_url)?.create_if_missing(true);
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(options)
+        .await?;
+
+    sqlx::migrate!("./migrations").run(&pool).await?;
+
+    match cli.command {
+        Command::Migrate => println!("Migrations applied."),
+        Command::User(command) => run_user(&pool, command).await?,
+        Command::Invite => {
+            let code = generate_invite_code();
+            sqlx::query!("INSERT INTO invite_codes (code) VALUES (?)", code)
+                .execute(&pool)
+                .await?;
+            println!("{}", code);
+        }
+        Command::Categories { user } => {
+            let user_id = match user {
+                Some(username) => Some(maintenance::user_id(&pool, &username).await?),
+                None => None,
+            };
+            for c in maintenance::category_counts(&pool, user_id).await? {
+                println!(
+                    "{}\t{}\t{} items\t{} ranked",
+                    c.username, c.name, c.item_count, c.ranked_count
+                );
+            }
+        }
+        Command::Export { username, output } => {
+            let user_id = maintenance::user_id(&pool, &username).await?;
+            let export = maintenance::export_user(&pool, user_id).await?;
+            let json = serde_json::to_string_pretty(&export)?;
+            match output {
+                Some(path) => std::fs::write(path, json)?,
+                None => println!("{}", json),
+            }
+        }
+        Command::Import { username, file } => {
+            let user_id = maintenance::user_id(&pool, &username).await?;
+            let export: Export = serde_json::from_slice(&std::fs::read(file)?)?;
+            maintenance::import_user(&pool, user_id, &export).await?;
+            println!(
+                "Imported {} categories into '{}'. Copy any images into uploads/ separately.",
+                export.categories.len(),
+                username
+            );
+        }
+        Command::Check => {
+            let problems = maintenance::check_integrity(&pool).await?;
+            if problems.is_empty() {
+                println!("No problems found.");
+            } else {
+                for problem in &problems {
+                    println!("{}", problem);
+                }
+                return Err(format!("{} problems found", problems.len()).into());
+            }
+        }
+        Command::CleanupImages { dry_run } => {
+            let orphans = maintenance::orphaned_images(&pool, ORPHAN_MIN_AGE).await?;
+            let total: u64 = orphans.iter().map(|(_, size)| size).sum();
+            for (url, _) in &orphans {
+                if !dry_run {
+                    delete_image(url).await?;
+                    maintenance::forget_upload(&pool, url).await?;
+                }
+                println!("{}", url);
+            }
+            let verb = if dry_run { "Would delete" } else { "Deleted" };
+            println!("{} {} images ({} bytes).", verb, orphans.len(), total);
+        }
+        Command::ImageVariants { all } => {
+            let settings = UploadSettings::from_env()?;
+            let missing = maintenance::missing_variants(all).await?;
+            let mut created = 0;
+            for url in &missing {
+                // A file that doesn't decode shouldn't stop the rest
+                match regenerate_variants(url, &settings) {
+                    Ok(()) => {
+                        // Its copies changed, and with them its disk usage
+                        maintenance::record_upload_size(&pool, url).await?;
+                        created += 1;
+                        println!("{}", url);
+                    }
+                    Err(e) => eprintln!("Skipped {}: {}", url, e),
+                }
+            }
+            println!(
+                "Created variants for {} of {} images.",
+                created,
+                missing.len()
+            );
+        }
+        Command::UploadSizes => {
+            let unmeasured = maintenance::unmeasured_uploads(&pool).await?;
+            let mut total = 0;
+            for url in &unmeasured {
+                total += maintenance::record_upload_size(&pool, url).await?;
+            }
+            println!(
+                "Recorded the size of {} uploads ({} bytes).",
+                unmeasured.len(),
+                total
+            );
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_user(pool: &SqlitePool, command: UserCommand) -> Result<(), Error> {
+    match command {
+        UserCommand::List => {
+            let users =
+                sqlx::query!("SELECT id, username, is_admin, disabled_at FROM users ORDER BY id")
+                    .fetch_all(pool)
+                    .await?;
+            for user in users {
+                let mut flags = Vec::new();
+                if user.is_admin {
+                    flags.push("admin");
+                }
+                if user.disabled_at.is_some() {
+                    flags.push("disabled");
+                }
+                println!("{}\t{}\t{}", user.id, user.username, flags.join(","));
+            }
+        }
+        UserCommand::Create { username, admin } => {
+            let password = read_password()?;
+            let user_id = create_user(pool, &username, &password, admin)
+                .await
+                .map_err(|(_, message)| message)?;
+            println!("Created '{}' with id {}.", username, user_id);
+        }
+        UserCommand::Delete { username, yes } => {
+            if !yes {
+                return Err("Deleting an account can't be undone; pass --yes to confirm".into());
+            }
+            let user_id = maintenance::user_id(pool, &username).await?;
+
+            let mut tx = pool.begin().await?;
+            let images = delete_user_data(&mut tx, user_id).await?;
+            tx.commit().await?;
+
+            for url in &images {
+                let _ = delete_image(url).await;
+            }
+            println!("Deleted '{}' and {} images.", username, images.len());
+        }
+        UserCommand::ResetPassword { username } => {
+            let user_id = maintenance::user_id(pool, &username).await?;
+            let password = read_password()?;
+
+            let mut tx = pool.begin().await?;
+            replace_password(&mut tx, user_id, &password)
+                .await
+                .map_err(|(_, message)| message)?;
+            tx.commit().await?;
+            println!("Password for '{}' changed; all sessions ended.", username);
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads a single line from stdin, so passwords stay out of shell history.
+fn read_password() -> Result<String, Error> {
+    eprint!("Password: ");
+    std::io::stderr().flush()?;
+
+    let mut password = String::new();
+    std::io::stdin().lock().read_line(&mut password)?;
+    Ok(password.trim_end_matches(['\r', '\n']).to_string())
+}
@@ -1,17 +1,41 @@
-use crate::models::{ApiItem, Claims, CreateItem, DbItem, UpdateItem};
-use crate::upload::delete_image;
+use crate::members::{
+    VISIBLE_CATEGORIES, find_category_by_name, find_editable_category_by_name, require_item_role,
+    require_role,
+};
+use crate::models::{
+    ApiItem, Category, CategoryRole, CategorySummary, Claims, CreateCategory, CreateItem,
+    DbCategorySummary, DbItem, PublicCategory, PublicItem, ReorderCategories, UpdateCategory,
+    UpdateItem,
+};
+use crate::ranking::rebalance_item_category;
+use crate::scoring::{score_item, score_items};
+use crate::upload::{delete_image, release_uploads, require_own_upload};
 use axum::{
     Json,
     extract::{Extension, Path, Query, State},
     http::StatusCode,
 };
+use rand::{Rng, distr::Alphanumeric};
 use serde::Deserialize;
-use sqlx::SqlitePool;
+use sqlx::{SqliteConnection, SqlitePool};
 use uuid::Uuid;
 
+// Longest accepted category icon, in characters
+const MAX_ICON_CHARS: usize = 16;
+// Long enough that share links can't be guessed
+const SHARE_SLUG_LEN: usize = 22;
+
 #[derive(Deserialize)]
 pub struct ListItemsQuery {
     category: Option<String>,
+    category_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct DeleteCategoryQuery {
+    // Without this, deleting a category that still has items is refused
+    #[serde(default)]
+    delete_items: bool,
 }
 
 pub async fn get_items(
@@ -19,27 +43,40 @@ pub async fn get_items(
     Extension(claims): Extension<Claims>,
     Query(query): Query<ListItemsQuery>,
 ) -> Result<Json<Vec<ApiItem>>, (StatusCode, String)> {
-    let mut sql = "SELECT i.id, i.name, i.notes, i.image_url, i.created_at, 
-                          i.rank_order,
-                          c.name as category 
-                   FROM items i 
-                   JOIN categories c ON i.category_id = c.id
-                   WHERE i.user_id = ?"
-        .to_string();
+    let category_id = match (query.category_id, query.category) {
+        (Some(id), _) => Some(id),
+        (None, Some(name)) => match find_category_by_name(&pool, &name, claims.uid)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        {
+            Some(id) => Some(id),
+            None => return Ok(Json(Vec::new())),
+        },
+        (None, None) => None,
+    };
+
+    let mut sql = format!(
+        "SELECT i.id, i.category_id, i.name, i.notes, i.image_url, i.created_at,
+                i.rank_order,
+                c.name as category
+         FROM items i
+         JOIN categories c ON i.category_id = c.id
+         WHERE i.category_id IN ({VISIBLE_CATEGORIES})"
+    );
 
     // We will bind 'claims.uid' first.
 
-    if let Some(_cat_name) = &query.category {
-        sql.push_str(" AND c.name = ?");
+    if category_id.is_some() {
+        sql.push_str(" AND i.category_id = ?");
     }
 
     sql.push_str(" ORDER BY i.rank_order DESC");
 
     let mut query_builder = sqlx::query_as::<_, DbItem>(&sql);
-    query_builder = query_builder.bind(claims.uid);
+    query_builder = query_builder.bind(claims.uid).bind(claims.uid);
 
-    if let Some(cat_name) = query.category {
-        query_builder = query_builder.bind(cat_name);
+    if let Some(category_id) = category_id {
+        query_builder = query_builder.bind(category_id);
     }
 
     let items = query_builder
@@ -47,50 +84,66 @@ pub async fn get_items(
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    let api_items: Vec<ApiItem> = items.into_iter().map(Into::into).collect();
+    let mut api_items: Vec<ApiItem> = items.into_iter().map(Into::into).collect();
+
+    score_items(&pool, &mut api_items)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     Ok(Json(api_items))
 }
 
-pub async fn get_item(
-    State(pool): State<SqlitePool>,
-    Extension(claims): Extension<Claims>,
-    Path(id): Path<String>,
-) -> Result<Json<ApiItem>, (StatusCode, String)> {
-    let item = sqlx::query_as::<_, DbItem>(
-        "SELECT i.id, i.name, i.notes, i.image_url, i.created_at,
+async fn fetch_item(pool: &SqlitePool, id: &str) -> Result<DbItem, sqlx::Error> {
+    sqlx::query_as::<_, DbItem>(
+        "SELECT i.id, i.category_id, i.name, i.notes, i.image_url, i.created_at,
                 i.rank_order,
-                c.name as category 
-         FROM items i 
+                c.name as category
+         FROM items i
          JOIN categories c ON i.category_id = c.id
-         WHERE i.id = ? AND i.user_id = ?",
+         WHERE i.id = ?",
     )
     .bind(id)
-    .bind(claims.uid)
-    .fetch_optional(&pool)
+    .fetch_one(pool)
     .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-    .ok_or((StatusCode::NOT_FOUND, "Item not found".to_string()))?;
+}
 
-    Ok(Json(item.into()))
+pub async fn get_item(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiItem>, (StatusCode, String)> {
+    require_item_role(&pool, &id, claims.uid, CategoryRole::Viewer).await?;
+
+    let item = fetch_item(&pool, &id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let item = score_item(&pool, item)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(item))
 }
 
+/// The category an item is being put in, checking the caller may add to it.
+/// A name that matches nothing the caller can add to creates a new category
+/// of their own.
 async fn get_or_create_category_id(
     pool: &SqlitePool,
     name: &str,
+    category_id: Option<&str>,
     user_id: i64,
-) -> Result<String, sqlx::Error> {
-    // Try to find existing for THIS user
-    let rec = sqlx::query!(
-        "SELECT id FROM categories WHERE name = ? AND user_id = ?",
-        name,
-        user_id
-    )
-    .fetch_optional(pool)
-    .await?;
+) -> Result<String, (StatusCode, String)> {
+    let existing = match category_id {
+        Some(id) => Some(id.to_string()),
+        None => find_editable_category_by_name(pool, name, user_id)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?,
+    };
 
-    if let Some(row) = rec {
-        return Ok(row.id);
+    if let Some(id) = existing {
+        require_role(pool, &id, user_id, CategoryRole::Editor).await?;
+        return Ok(id);
     }
 
     // Create new
@@ -102,7 +155,8 @@ async fn get_or_create_category_id(
         user_id
     )
     .execute(pool)
-    .await?;
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     Ok(new_id)
 }
@@ -112,9 +166,17 @@ pub async fn create_item(
     Extension(claims): Extension<Claims>,
     Json(payload): Json<CreateItem>,
 ) -> Result<Json<ApiItem>, (StatusCode, String)> {
-    let category_id = get_or_create_category_id(&pool, &payload.category, claims.uid)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if let Some(url) = &payload.image_url {
+        require_own_upload(&pool, url, claims.uid).await?;
+    }
+
+    let category_id = get_or_create_category_id(
+        &pool,
+        &payload.category,
+        payload.category_id.as_deref(),
+        claims.uid,
+    )
+    .await?;
 
     let item_id = Uuid::new_v4().to_string();
 
@@ -122,7 +184,7 @@ pub async fn create_item(
     let rank_order: Option<f64> = None;
 
     sqlx::query!(
-        "INSERT INTO items (id, category_id, name, notes, image_url, rank_order, user_id) VALUES (?, ?, ?, ?, ?, ?, ?)",
+        "INSERT INTO items (id, category_id, name, notes, image_url, rank_order, user_id, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)",
         item_id,
         category_id,
         payload.name,
@@ -136,20 +198,15 @@ pub async fn create_item(
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     // Fetch back the full item
-    let item = sqlx::query_as::<_, DbItem>(
-        "SELECT i.id, i.name, i.notes, i.image_url, i.created_at,
-                i.rank_order,
-                c.name as category 
-         FROM items i 
-         JOIN categories c ON i.category_id = c.id
-         WHERE i.id = ?",
-    )
-    .bind(&item_id)
-    .fetch_one(&pool)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let item = fetch_item(&pool, &item_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    Ok(Json(item.into()))
+    let item = score_item(&pool, item)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(item))
 }
 
 pub async fn update_item(
@@ -158,21 +215,25 @@ pub async fn update_item(
     Path(id): Path<String>,
     Json(payload): Json<UpdateItem>,
 ) -> Result<Json<ApiItem>, (StatusCode, String)> {
-    // Check if item exists first AND belongs to user
-    let existing_item = sqlx::query!(
-        "SELECT id, image_url FROM items WHERE id = ? AND user_id = ?",
-        id,
-        claims.uid
-    )
-    .fetch_optional(&pool)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-    .ok_or((StatusCode::NOT_FOUND, "Item not found".to_string()))?;
+    require_item_role(&pool, &id, claims.uid, CategoryRole::Editor).await?;
 
-    if let Some(cat_name) = &payload.category {
-        let category_id = get_or_create_category_id(&pool, cat_name, claims.uid)
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let existing_item = sqlx::query!("SELECT id, image_url FROM items WHERE id = ?", id)
+        .fetch_one(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // Editors resave whatever image is already there, whoever uploaded it
+    if let Some(url) = &payload.image_url
+        && existing_item.image_url.as_ref() != Some(url)
+    {
+        require_own_upload(&pool, url, claims.uid).await?;
+    }
+
+    if payload.category.is_some() || payload.category_id.is_some() {
+        let name = payload.category.as_deref().unwrap_or_default();
+        let category_id =
+            get_or_create_category_id(&pool, name, payload.category_id.as_deref(), claims.uid)
+                .await?;
 
         sqlx::query!(
             "UPDATE items SET category_id = ? WHERE id = ?",
@@ -197,38 +258,46 @@ pub async fn update_item(
             .ok();
     }
     if let Some(val) = payload.image_url {
-        // If there was an old image and it's different from the new one, delete the old one
-        if let Some(old_url) = existing_item.image_url.filter(|u| u != &val) {
-            let _ = delete_image(&old_url).await;
-        }
-
         sqlx::query!("UPDATE items SET image_url = ? WHERE id = ?", val, id)
             .execute(&pool)
             .await
             .ok();
+
+        // If there was an old image and it's different from the new one, delete the old one
+        if let Some(old_url) = existing_item.image_url.filter(|u| u != &val) {
+            discard_images(&pool, vec![old_url]).await?;
+        }
     }
     if let Some(val) = payload.rank_order {
         sqlx::query!("UPDATE items SET rank_order = ? WHERE id = ?", val, id)
             .execute(&pool)
             .await
             .ok();
+
+        // Midpoint insertions eventually run out of float precision
+        rebalance_item_category(&pool, &id)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
     }
 
-    // Return updated item
-    let item = sqlx::query_as::<_, DbItem>(
-        "SELECT i.id, i.name, i.notes, i.image_url, i.created_at,
-                i.rank_order,
-                c.name as category 
-         FROM items i 
-         JOIN categories c ON i.category_id = c.id
-         WHERE i.id = ?",
+    sqlx::query!(
+        "UPDATE items SET updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        id
     )
-    .bind(&id)
-    .fetch_one(&pool)
+    .execute(&pool)
     .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    Ok(Json(item.into()))
+    // Return updated item
+    let item = fetch_item(&pool, &id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let item = score_item(&pool, item)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(item))
 }
 
 pub async fn delete_item(
@@ -236,21 +305,29 @@ pub async fn delete_item(
     Extension(claims): Extension<Claims>,
     Path(id): Path<String>,
 ) -> Result<StatusCode, (StatusCode, String)> {
-    // Get item first to find image_url AND check ownership
-    let item = sqlx::query!(
-        "SELECT image_url FROM items WHERE id = ? AND user_id = ?",
+    require_item_role(&pool, &id, claims.uid, CategoryRole::Editor).await?;
+
+    // Get item first to find image_url
+    let item = sqlx::query!("SELECT image_url FROM items WHERE id = ?", id)
+        .fetch_one(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // Drop any unfinished ranking of this item
+    sqlx::query!("DELETE FROM rank_sessions WHERE item_id = ?", id)
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // Comparison history is meaningless without both sides
+    sqlx::query!(
+        "DELETE FROM comparisons WHERE winner_id = ? OR loser_id = ?",
         id,
-        claims.uid
+        id
     )
-    .fetch_optional(&pool)
+    .execute(&pool)
     .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-    .ok_or((StatusCode::NOT_FOUND, "Item not found".to_string()))?;
-
-    // Delete image if exists
-    if let Some(url) = item.image_url {
-        let _ = delete_image(&url).await;
-    }
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     // Delete item from DB
     sqlx::query!("DELETE FROM items WHERE id = ?", id)
@@ -258,21 +335,596 @@ pub async fn delete_item(
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
+    // Delete image if exists
+    discard_images(&pool, item.image_url.into_iter().collect()).await?;
+
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// Deletes the files of uploads among `urls` that nothing uses any more.
+async fn discard_images(pool: &SqlitePool, urls: Vec<String>) -> Result<(), (StatusCode, String)> {
+    let mut conn = pool
+        .acquire()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let released = release_uploads(&mut conn, urls)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    for url in released {
+        let _ = delete_image(&url).await;
+    }
+    Ok(())
+}
+
 pub async fn get_categories(
     State(pool): State<SqlitePool>,
     Extension(claims): Extension<Claims>,
-) -> Result<Json<Vec<String>>, (StatusCode, String)> {
-    let categories = sqlx::query!(
-        "SELECT name FROM categories WHERE user_id = ? ORDER BY name",
+) -> Result<Json<Vec<CategorySummary>>, (StatusCode, String)> {
+    // Members keep their own order in category_members.position
+    let categories = sqlx::query_as::<_, DbCategorySummary>(
+        "SELECT * FROM (
+             SELECT c.id, c.name, c.description, c.icon, c.cover_image_url,
+                    CASE WHEN c.user_id = ? THEN c.position ELSE m.position END as position,
+                    c.scoring_mode, c.is_public, c.share_slug, c.created_at,
+                    COUNT(i.id) as item_count,
+                    COUNT(i.rank_order) as ranked_count,
+                    COALESCE(MAX(COALESCE(i.updated_at, i.created_at)), c.created_at) as updated_at,
+                    CASE WHEN c.user_id = ? THEN 'owner' ELSE m.role END as role
+             FROM categories c
+             LEFT JOIN category_members m ON m.category_id = c.id AND m.user_id = ?
+             LEFT JOIN items i ON i.category_id = c.id
+             WHERE c.user_id = ? OR m.user_id IS NOT NULL
+             GROUP BY c.id
+         )
+         ORDER BY position IS NULL, position, name",
+    )
+    .bind(claims.uid)
+    .bind(claims.uid)
+    .bind(claims.uid)
+    .bind(claims.uid)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // Highest ranked item of every category in one pass
+    let top_items = sqlx::query_as::<_, DbItem>(&format!(
+        "SELECT id, category_id, name, notes, image_url, created_at, rank_order, category
+             FROM (
+                 SELECT i.id, i.category_id, i.name, i.notes, i.image_url, i.created_at,
+                        i.rank_order,
+                        c.name as category,
+                        ROW_NUMBER() OVER (
+                            PARTITION BY i.category_id ORDER BY i.rank_order DESC
+                        ) as position
+                 FROM items i
+                 JOIN categories c ON i.category_id = c.id
+                 WHERE i.category_id IN ({VISIBLE_CATEGORIES}) AND i.rank_order IS NOT NULL
+             )
+             WHERE position = 1"
+    ))
+    .bind(claims.uid)
+    .bind(claims.uid)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let mut top_items: Vec<ApiItem> = top_items.into_iter().map(Into::into).collect();
+    score_items(&pool, &mut top_items)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let summaries = categories
+        .into_iter()
+        .map(|category| {
+            let top_item = top_items
+                .iter()
+                .find(|item| item.category_id == category.id)
+                .cloned();
+
+            CategorySummary {
+                unranked_count: category.item_count - category.ranked_count,
+                id: category.id,
+                name: category.name,
+                description: category.description,
+                icon: category.icon,
+                cover_image_url: category.cover_image_url,
+                position: category.position,
+                scoring_mode: category.scoring_mode,
+                is_public: category.is_public,
+                share_slug: category.share_slug,
+                created_at: category.created_at,
+                updated_at: category.updated_at,
+                item_count: category.item_count,
+                ranked_count: category.ranked_count,
+                top_item,
+                role: category.role,
+            }
+        })
+        .collect();
+
+    Ok(Json(summaries))
+}
+
+async fn fetch_category(
+    pool: &SqlitePool,
+    id: &str,
+    user_id: i64,
+) -> Result<Option<Category>, sqlx::Error> {
+    sqlx::query_as::<_, Category>(
+        "SELECT c.id, c.name, c.description, c.icon, c.cover_image_url,
+                CASE WHEN c.user_id = ? THEN c.position ELSE m.position END as position,
+                c.scoring_mode, c.is_public, c.share_slug, c.created_at,
+                CASE WHEN c.user_id = ? THEN 'owner' ELSE m.role END as role
+         FROM categories c
+         LEFT JOIN category_members m ON m.category_id = c.id AND m.user_id = ?
+         WHERE c.id = ? AND (c.user_id = ? OR m.user_id IS NOT NULL)",
+    )
+    .bind(user_id)
+    .bind(user_id)
+    .bind(user_id)
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Removes every item in a category along with its ranking history and
+/// returns the image URLs they referenced, for `release_uploads`.
+async fn purge_category_items(
+    conn: &mut SqliteConnection,
+    category_id: &str,
+) -> Result<Vec<String>, sqlx::Error> {
+    let image_urls = sqlx::query_scalar::<_, String>(
+        "SELECT image_url FROM items WHERE category_id = ? AND image_url IS NOT NULL",
+    )
+    .bind(category_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        "DELETE FROM rank_sessions WHERE item_id IN (SELECT id FROM items WHERE category_id = ?)",
+        category_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        "DELETE FROM comparisons
+         WHERE winner_id IN (SELECT id FROM items WHERE category_id = ?)
+            OR loser_id IN (SELECT id FROM items WHERE category_id = ?)",
+        category_id,
+        category_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!("DELETE FROM items WHERE category_id = ?", category_id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(image_urls)
+}
+
+fn generate_share_slug() -> String {
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(SHARE_SLUG_LEN)
+        .map(char::from)
+        .collect()
+}
+
+fn validate_category_name(name: &str) -> Result<(), (StatusCode, String)> {
+    if name.trim().is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Category name cannot be empty".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn validate_category_icon(icon: &str) -> Result<(), (StatusCode, String)> {
+    // Room for multi-codepoint emoji, not for essays
+    if icon.chars().count() > MAX_ICON_CHARS {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Icon must be at most {} characters", MAX_ICON_CHARS),
+        ));
+    }
+    Ok(())
+}
+
+fn map_category_name_conflict(e: sqlx::Error) -> (StatusCode, String) {
+    match e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => (
+            StatusCode::CONFLICT,
+            "A category with that name already exists".to_string(),
+        ),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+pub async fn create_category(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<CreateCategory>,
+) -> Result<Json<Category>, (StatusCode, String)> {
+    validate_category_name(&payload.name)?;
+    if let Some(icon) = &payload.icon {
+        validate_category_icon(icon)?;
+    }
+    if let Some(url) = &payload.cover_image_url {
+        require_own_upload(&pool, url, claims.uid).await?;
+    }
+
+    let category_id = Uuid::new_v4().to_string();
+    sqlx::query!(
+        "INSERT INTO categories (id, name, user_id, description, icon, cover_image_url)
+         VALUES (?, ?, ?, ?, ?, ?)",
+        category_id,
+        payload.name,
+        claims.uid,
+        payload.description,
+        payload.icon,
+        payload.cover_image_url
+    )
+    .execute(&pool)
+    .await
+    .map_err(map_category_name_conflict)?;
+
+    let category = fetch_category(&pool, &category_id, claims.uid)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Category not found".to_string()))?;
+
+    Ok(Json(category))
+}
+
+pub async fn update_category(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    Json(payload): Json<UpdateCategory>,
+) -> Result<Json<Category>, (StatusCode, String)> {
+    let existing = fetch_category(&pool, &id, claims.uid)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Category not found".to_string()))?;
+
+    let changes_category = payload.name.is_some()
+        || payload.description.is_some()
+        || payload.icon.is_some()
+        || payload.cover_image_url.is_some()
+        || payload.is_public.is_some()
+        || payload.scoring_mode.is_some();
+    if changes_category && existing.role != CategoryRole::Owner {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Only the owner can do that".to_string(),
+        ));
+    }
+
+    if let Some(name) = &payload.name {
+        validate_category_name(name)?;
+    }
+    if let Some(icon) = &payload.icon {
+        validate_category_icon(icon)?;
+    }
+    if let Some(url) = &payload.cover_image_url
+        && existing.cover_image_url.as_ref() != Some(url)
+    {
+        require_own_upload(&pool, url, claims.uid).await?;
+    }
+
+    if let Some(name) = payload.name {
+        sqlx::query!("UPDATE categories SET name = ? WHERE id = ?", name, id)
+            .execute(&pool)
+            .await
+            .map_err(map_category_name_conflict)?;
+    }
+    if let Some(val) = payload.description {
+        sqlx::query!(
+            "UPDATE categories SET description = ? WHERE id = ?",
+            val,
+            id
+        )
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+    if let Some(val) = payload.icon {
+        sqlx::query!("UPDATE categories SET icon = ? WHERE id = ?", val, id)
+            .execute(&pool)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+    if let Some(val) = payload.cover_image_url {
+        sqlx::query!(
+            "UPDATE categories SET cover_image_url = ? WHERE id = ?",
+            val,
+            id
+        )
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        // Same as items: a replaced cover image is no longer needed
+        if let Some(old_url) = existing.cover_image_url.filter(|u| u != &val) {
+            discard_images(&pool, vec![old_url]).await?;
+        }
+    }
+    if let Some(val) = payload.position {
+        if existing.role == CategoryRole::Owner {
+            sqlx::query!("UPDATE categories SET position = ? WHERE id = ?", val, id)
+                .execute(&pool)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        } else {
+            sqlx::query!(
+                "UPDATE category_members SET position = ? WHERE category_id = ? AND user_id = ?",
+                val,
+                id,
+                claims.uid
+            )
+            .execute(&pool)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        }
+    }
+    if let Some(val) = payload.is_public {
+        // Keep an existing link stable; only mint a slug when first published
+        let share_slug = match (val, existing.share_slug) {
+            (true, Some(slug)) => Some(slug),
+            (true, None) => Some(generate_share_slug()),
+            (false, _) => None,
+        };
+
+        sqlx::query!(
+            "UPDATE categories SET is_public = ?, share_slug = ? WHERE id = ?",
+            val,
+            share_slug,
+            id
+        )
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+    if let Some(val) = payload.scoring_mode {
+        sqlx::query("UPDATE categories SET scoring_mode = ? WHERE id = ?")
+            .bind(val)
+            .bind(&id)
+            .execute(&pool)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+
+    let category = fetch_category(&pool, &id, claims.uid)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Category not found".to_string()))?;
+
+    Ok(Json(category))
+}
+
+/// Sets the manual order of the listed categories. Categories left out lose
+/// their position and fall back to alphabetical order after the listed ones.
+pub async fn reorder_categories(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<ReorderCategories>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    sqlx::query!(
+        "UPDATE categories SET position = NULL WHERE user_id = ?",
         claims.uid
     )
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    sqlx::query!(
+        "UPDATE category_members SET position = NULL WHERE user_id = ?",
+        claims.uid
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    for (position, id) in payload.ids.iter().enumerate() {
+        let position = position as i64;
+        let mut result = sqlx::query!(
+            "UPDATE categories SET position = ? WHERE id = ? AND user_id = ?",
+            position,
+            id,
+            claims.uid
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        // Shared categories are ordered in the member's own row
+        if result.rows_affected() == 0 {
+            result = sqlx::query!(
+                "UPDATE category_members SET position = ? WHERE category_id = ? AND user_id = ?",
+                position,
+                id,
+                claims.uid
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        }
+
+        // Dropping tx rolls back everything done so far
+        if result.rows_affected() == 0 {
+            return Err((StatusCode::NOT_FOUND, format!("Category {} not found", id)));
+        }
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn delete_category(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    Query(query): Query<DeleteCategoryQuery>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    require_role(&pool, &id, claims.uid, CategoryRole::Owner).await?;
+
+    let category = fetch_category(&pool, &id, claims.uid)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Category not found".to_string()))?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let item_count =
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM items WHERE category_id = ?")
+            .bind(&id)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if item_count > 0 && !query.delete_items {
+        return Err((
+            StatusCode::CONFLICT,
+            "Category still has items; pass delete_items=true to delete them too".to_string(),
+        ));
+    }
+
+    let mut image_urls = purge_category_items(&mut tx, &id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    image_urls.extend(category.cover_image_url);
+
+    // Comparisons made here between items that have since moved elsewhere
+    sqlx::query!("DELETE FROM comparisons WHERE category_id = ?", id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    sqlx::query!("DELETE FROM category_members WHERE category_id = ?", id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    sqlx::query!("DELETE FROM categories WHERE id = ?", id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let image_urls = release_uploads(&mut tx, image_urls)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    for url in image_urls {
+        let _ = delete_image(&url).await;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn delete_category_items(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    require_role(&pool, &id, claims.uid, CategoryRole::Owner).await?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let image_urls = purge_category_items(&mut tx, &id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let image_urls = release_uploads(&mut tx, image_urls)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    for url in image_urls {
+        let _ = delete_image(&url).await;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Unauthenticated view of a public category's ranked items.
+pub async fn get_public_category(
+    State(pool): State<SqlitePool>,
+    Path(share_slug): Path<String>,
+) -> Result<Json<PublicCategory>, (StatusCode, String)> {
+    let category = sqlx::query!(
+        "SELECT id, name, description, icon, cover_image_url
+         FROM categories
+         WHERE share_slug = ? AND is_public = 1",
+        share_slug
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, "List not found".to_string()))?;
+
+    let items = sqlx::query_as::<_, DbItem>(
+        "SELECT i.id, i.category_id, i.name, i.notes, i.image_url, i.created_at,
+                i.rank_order,
+                c.name as category
+         FROM items i
+         JOIN categories c ON i.category_id = c.id
+         WHERE i.category_id = ? AND i.rank_order IS NOT NULL
+         ORDER BY i.rank_order DESC",
+    )
+    .bind(&category.id)
     .fetch_all(&pool)
     .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    let names = categories.into_iter().map(|rec| rec.name).collect();
-    Ok(Json(names))
+    let mut items: Vec<ApiItem> = items.into_iter().map(Into::into).collect();
+    score_items(&pool, &mut items)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let items = items
+        .into_iter()
+        .enumerate()
+        .map(|(idx, item)| PublicItem {
+            rank: idx + 1,
+            name: item.name,
+            notes: item.notes,
+            image_url: item.image_url,
+            normalized_score: item.normalized_score,
+            score_interval: item.score_interval,
+        })
+        .collect();
+
+    Ok(Json(PublicCategory {
+        name: category.name,
+        description: category.description,
+        icon: category.icon,
+        cover_image_url: category.cover_image_url,
+        items,
+    }))
 }
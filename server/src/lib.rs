@@ -1,16 +1,46 @@
+pub mod account;
+pub mod admin;
 pub mod auth;
 pub mod handlers;
+pub mod maintenance;
+pub mod members;
 pub mod models;
+pub mod ranking;
+pub mod scoring;
+pub mod sessions;
+pub mod state;
+pub mod throttle;
+pub mod tokens;
+pub mod totp;
 pub mod upload;
 
 use axum::{
-    Router, middleware,
-    routing::{get, post},
+    Router,
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{delete, get, patch, post, put},
 };
-use sqlx::SqlitePool;
+use state::AppState;
 use tower_http::services::{ServeDir, ServeFile};
 
-pub fn create_router(pool: SqlitePool) -> Router {
+pub fn create_router(state: AppState) -> Router {
+    let admin_routes = Router::new()
+        .route(
+            "/api/admin/users",
+            get(admin::get_users).post(admin::create_user),
+        )
+        .route("/api/admin/users/{id}", patch(admin::update_user))
+        .route(
+            "/api/admin/users/{id}/password",
+            post(admin::reset_password),
+        )
+        .route(
+            "/api/admin/invites",
+            get(admin::get_invites).post(admin::create_invite),
+        )
+        .route("/api/admin/storage", get(admin::get_storage))
+        .route_layer(middleware::from_fn(auth::require_admin));
+
     let auth_routes = Router::new()
         .route(
             "/api/items",
@@ -22,13 +52,91 @@ pub fn create_router(pool: SqlitePool) -> Router {
                 .patch(handlers::update_item)
                 .delete(handlers::delete_item),
         )
-        .route("/api/categories", get(handlers::get_categories))
-        .route("/api/upload", post(upload::upload_image))
-        .layer(middleware::from_fn(auth::auth_middleware));
+        .route(
+            "/api/items/{id}/comparisons",
+            get(ranking::get_item_comparisons),
+        )
+        .route(
+            "/api/categories",
+            get(handlers::get_categories).post(handlers::create_category),
+        )
+        .route("/api/categories/order", put(handlers::reorder_categories))
+        .route(
+            "/api/categories/{id}",
+            patch(handlers::update_category).delete(handlers::delete_category),
+        )
+        .route(
+            "/api/categories/{id}/items",
+            delete(handlers::delete_category_items),
+        )
+        .route(
+            "/api/categories/{id}/members",
+            get(members::get_members).post(members::add_member),
+        )
+        .route(
+            "/api/categories/{id}/members/{user_id}",
+            patch(members::update_member).delete(members::remove_member),
+        )
+        .route(
+            "/api/categories/{id}/rebalance",
+            post(ranking::rebalance_category),
+        )
+        .route("/api/rank-sessions", post(ranking::start_rank_session))
+        .route(
+            "/api/rank-sessions/{id}",
+            get(ranking::get_rank_session).delete(ranking::delete_rank_session),
+        )
+        .route(
+            "/api/rank-sessions/{id}/answer",
+            post(ranking::answer_rank_session),
+        )
+        .route(
+            "/api/account",
+            get(account::get_account)
+                .patch(account::update_account)
+                .delete(account::delete_account),
+        )
+        .route("/api/account/password", post(account::change_password))
+        .route(
+            "/api/account/totp",
+            post(totp::enroll_totp).delete(totp::disable_totp),
+        )
+        .route("/api/account/totp/confirm", post(totp::confirm_totp))
+        .route(
+            "/api/account/totp/recovery-codes",
+            post(totp::regenerate_recovery_codes),
+        )
+        .route("/api/logout", post(sessions::logout))
+        .route(
+            "/api/sessions",
+            get(sessions::get_sessions).delete(sessions::delete_other_sessions),
+        )
+        .route("/api/sessions/{id}", delete(sessions::delete_session))
+        .route(
+            "/api/tokens",
+            get(tokens::get_tokens).post(tokens::create_token),
+        )
+        .route("/api/tokens/{id}", delete(tokens::delete_token))
+        .route(
+            "/api/upload",
+            post(upload::upload_image)
+                .layer(DefaultBodyLimit::max(state.upload_settings.body_limit())),
+        )
+        .merge(admin_routes)
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::auth_middleware,
+        ));
 
     let public_routes = Router::new()
         .route("/api/login", post(auth::login))
-        .nest_service("/uploads", ServeDir::new("uploads"))
+        .route("/api/register", post(auth::register))
+        .route("/api/token/refresh", post(sessions::refresh_token))
+        .route(
+            "/api/public/{share_slug}",
+            get(handlers::get_public_category),
+        )
+        .route("/uploads/{filename}", get(upload::serve_upload))
         .fallback_service(
             ServeDir::new("../client/dist").fallback(ServeFile::new("../client/dist/index.html")),
         );
@@ -37,5 +145,5 @@ pub fn create_router(pool: SqlitePool) -> Router {
         .merge(auth_routes)
         .merge(public_routes)
         .layer(tower_http::cors::CorsLayer::permissive())
-        .with_state(pool)
+        .with_state(state)
 }
@@ -1,6 +1,7 @@
-use server::create_router;
+use server::{auth::JwtKeys, create_router, state::AppState, upload::UploadSettings};
 use sqlx::sqlite::SqlitePoolOptions;
 
+use std::net::SocketAddr;
 use std::path::Path;
 use tokio::fs;
 
@@ -11,6 +12,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     tracing_subscriber::fmt::init();
 
+    // Refuse to start without signing keys rather than fall back to a guessable one
+    let jwt_keys = JwtKeys::from_env()?;
+    let upload_settings = UploadSettings::from_env()?;
+
     let db_url = std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite:data.db".to_string());
 
     // Ensure database file exists
@@ -38,7 +43,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Migrations applied successfully.");
 
     // Build router
-    let app = create_router(pool);
+    let app = create_router(AppState::new(pool, jwt_keys).with_upload_settings(upload_settings));
 
     // Run server
     let port = std::env::var("PORT").unwrap_or_else(|_| "3000".to_string());
@@ -47,7 +52,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     println!("listening on {}", addr);
     let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    // The peer address feeds login throttling
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
 
     Ok(())
 }
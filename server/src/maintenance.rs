@@ -0,0 +1,373 @@
+//! Offline upkeep for `stuff-admin`: listings, export/import, integrity checks
+//! and upload cleanup. Nothing here is reachable over HTTP.
+
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, SystemTime};
+use uuid::Uuid;
+
+use crate::models::{CategoryCount, Export, ExportCategory, ExportComparison, ExportItem};
+use crate::upload::{image_size, image_variants, is_original, upload_key, uploaded_files};
+
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+
+pub const EXPORT_VERSION: u32 = 1;
+
+/// Looks up a user id by name, ignoring case like logins do.
+pub async fn user_id(pool: &SqlitePool, username: &str) -> Result<i64, Error> {
+    sqlx::query_scalar::<_, i64>("SELECT id FROM users WHERE username = ? COLLATE NOCASE")
+        .bind(username)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| format!("No user named '{}'", username).into())
+}
+
+/// Every category with its item counts, optionally for a single user.
+pub async fn category_counts(
+    pool: &SqlitePool,
+    user_id: Option<i64>,
+) -> Result<Vec<CategoryCount>, Error> {
+    let counts = sqlx::query_as::<_, CategoryCount>(
+        "SELECT u.username, c.name,
+                COUNT(i.id) as item_count,
+                COUNT(i.rank_order) as ranked_count
+         FROM categories c
+         JOIN users u ON c.user_id = u.id
+         LEFT JOIN items i ON i.category_id = c.id
+         WHERE ? IS NULL OR c.user_id = ?
+         GROUP BY c.id
+         ORDER BY u.username, c.position IS NULL, c.position, c.name",
+    )
+    .bind(user_id)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(counts)
+}
+
+pub async fn export_user(pool: &SqlitePool, user_id: i64) -> Result<Export, Error> {
+    let mut categories = sqlx::query_as::<_, ExportCategory>(
+        "SELECT id, name, description, icon, cover_image_url, position, scoring_mode, created_at
+         FROM categories
+         WHERE user_id = ?
+         ORDER BY position IS NULL, position, name",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    for category in &mut categories {
+        category.items = sqlx::query_as::<_, ExportItem>(
+            "SELECT id, name, notes, image_url, rank_order, created_at, updated_at
+             FROM items
+             WHERE category_id = ?
+             ORDER BY created_at",
+        )
+        .bind(&category.id)
+        .fetch_all(pool)
+        .await?;
+
+        category.comparisons = sqlx::query_as::<_, ExportComparison>(
+            "SELECT winner_id, loser_id, created_at
+             FROM comparisons
+             WHERE category_id = ?
+             ORDER BY created_at",
+        )
+        .bind(&category.id)
+        .fetch_all(pool)
+        .await?;
+    }
+
+    Ok(Export {
+        version: EXPORT_VERSION,
+        categories,
+    })
+}
+
+/// Adds the categories in `export` to a user's account, all or nothing.
+/// Categories come back private; publishing them again mints new links.
+pub async fn import_user(pool: &SqlitePool, user_id: i64, export: &Export) -> Result<(), Error> {
+    if export.version != EXPORT_VERSION {
+        return Err(format!("Unsupported export version {}", export.version).into());
+    }
+
+    let mut tx = pool.begin().await?;
+
+    for category in &export.categories {
+        let existing = sqlx::query_scalar::<_, String>(
+            "SELECT id FROM categories WHERE user_id = ? AND name = ?",
+        )
+        .bind(user_id)
+        .bind(&category.name)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if existing.is_some() {
+            return Err(format!("Category '{}' already exists", category.name).into());
+        }
+
+        let category_id = Uuid::new_v4().to_string();
+        sqlx::query(
+            "INSERT INTO categories
+                (id, name, user_id, description, icon, cover_image_url, position, scoring_mode, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&category_id)
+        .bind(&category.name)
+        .bind(user_id)
+        .bind(&category.description)
+        .bind(&category.icon)
+        .bind(&category.cover_image_url)
+        .bind(category.position)
+        .bind(category.scoring_mode)
+        .bind(category.created_at)
+        .execute(&mut *tx)
+        .await?;
+
+        if let Some(url) = &category.cover_image_url {
+            claim_upload(&mut tx, url, user_id).await?;
+        }
+
+        let mut item_ids = HashMap::new();
+        for item in &category.items {
+            let item_id = Uuid::new_v4().to_string();
+            sqlx::query(
+                "INSERT INTO items
+                    (id, category_id, user_id, name, notes, image_url, rank_order, created_at, updated_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(&item_id)
+            .bind(&category_id)
+            .bind(user_id)
+            .bind(&item.name)
+            .bind(&item.notes)
+            .bind(&item.image_url)
+            .bind(item.rank_order)
+            .bind(item.created_at)
+            .bind(item.updated_at)
+            .execute(&mut *tx)
+            .await?;
+            item_ids.insert(item.id.as_str(), item_id);
+
+            if let Some(url) = &item.image_url {
+                claim_upload(&mut tx, url, user_id).await?;
+            }
+        }
+
+        for comparison in &category.comparisons {
+            let (Some(winner_id), Some(loser_id)) = (
+                item_ids.get(comparison.winner_id.as_str()),
+                item_ids.get(comparison.loser_id.as_str()),
+            ) else {
+                return Err(format!(
+                    "Comparison in '{}' refers to an item that isn't in the export",
+                    category.name
+                )
+                .into());
+            };
+
+            sqlx::query(
+                "INSERT INTO comparisons (id, user_id, category_id, winner_id, loser_id, created_at)
+                 VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(Uuid::new_v4().to_string())
+            .bind(user_id)
+            .bind(&category_id)
+            .bind(winner_id)
+            .bind(loser_id)
+            .bind(comparison.created_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Records an imported local image as the user's upload, unless it already
+/// belongs to someone.
+async fn claim_upload(conn: &mut SqliteConnection, url: &str, user_id: i64) -> Result<(), Error> {
+    if url.starts_with("/uploads/") {
+        sqlx::query("INSERT OR IGNORE INTO uploads (url, user_id) VALUES (?, ?)")
+            .bind(url)
+            .bind(user_id)
+            .execute(conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Forgets uploads whose files `cleanup-images` deleted.
+pub async fn forget_upload(pool: &SqlitePool, url: &str) -> Result<(), Error> {
+    sqlx::query("DELETE FROM uploads WHERE url = ?")
+        .bind(url)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Uploads whose disk usage hasn't been recorded, such as those saved before
+/// it was.
+pub async fn unmeasured_uploads(pool: &SqlitePool) -> Result<Vec<String>, Error> {
+    let urls =
+        sqlx::query_scalar::<_, String>("SELECT url FROM uploads WHERE size IS NULL ORDER BY url")
+            .fetch_all(pool)
+            .await?;
+    Ok(urls)
+}
+
+/// Records what an upload's files currently take up on disk and returns it.
+pub async fn record_upload_size(pool: &SqlitePool, url: &str) -> Result<u64, Error> {
+    let size = image_size(url)?;
+    sqlx::query("UPDATE uploads SET size = ? WHERE url = ?")
+        .bind(size as i64)
+        .bind(url)
+        .execute(pool)
+        .await?;
+    Ok(size)
+}
+
+/// Every image URL still referenced by an item or category cover.
+async fn referenced_images(pool: &SqlitePool) -> Result<Vec<String>, Error> {
+    let urls = sqlx::query_scalar::<_, String>(
+        "SELECT image_url FROM items WHERE image_url IS NOT NULL
+         UNION
+         SELECT cover_image_url FROM categories WHERE cover_image_url IS NOT NULL",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(urls)
+}
+
+/// Describes every problem found; an empty list means the database is healthy.
+pub async fn check_integrity(pool: &SqlitePool) -> Result<Vec<String>, Error> {
+    let mut problems = Vec::new();
+
+    let results = sqlx::query_scalar::<_, String>("PRAGMA integrity_check")
+        .fetch_all(pool)
+        .await?;
+    problems.extend(results.into_iter().filter(|r| r != "ok"));
+
+    let broken = sqlx::query_as::<_, (String, Option<i64>, String)>(
+        "SELECT \"table\", rowid, parent FROM pragma_foreign_key_check",
+    )
+    .fetch_all(pool)
+    .await?;
+    for (table, rowid, parent) in broken {
+        problems.push(format!(
+            "{} row {} points at a missing {} row",
+            table,
+            rowid.map_or("?".to_string(), |id| id.to_string()),
+            parent
+        ));
+    }
+
+    let mismatched = sqlx::query_as::<_, (String, String)>(
+        "SELECT i.id, c.name
+         FROM items i
+         JOIN categories c ON i.category_id = c.id
+         WHERE i.user_id IS NOT c.user_id
+           AND NOT EXISTS (SELECT 1 FROM category_members m
+                           WHERE m.category_id = c.id AND m.user_id = i.user_id)",
+    )
+    .fetch_all(pool)
+    .await?;
+    for (item_id, category) in mismatched {
+        problems.push(format!(
+            "Item {} belongs to someone who can't see its category '{}'",
+            item_id, category
+        ));
+    }
+
+    let unowned = sqlx::query_scalar::<_, String>(
+        "SELECT 'Item ' || id FROM items WHERE user_id IS NULL
+         UNION ALL
+         SELECT 'Category ' || id FROM categories WHERE user_id IS NULL",
+    )
+    .fetch_all(pool)
+    .await?;
+    for row in unowned {
+        problems.push(format!("{} has no owner", row));
+    }
+
+    let unlinked = sqlx::query_scalar::<_, String>(
+        "SELECT name FROM categories WHERE is_public = 1 AND share_slug IS NULL",
+    )
+    .fetch_all(pool)
+    .await?;
+    for name in unlinked {
+        problems.push(format!(
+            "Category '{}' is public but has no share link",
+            name
+        ));
+    }
+
+    let on_disk: HashSet<String> = uploaded_files()
+        .await?
+        .into_iter()
+        .map(|(url, _)| url)
+        .collect();
+    for url in referenced_images(pool).await? {
+        if url.starts_with("/uploads/") && !on_disk.contains(&url) {
+            problems.push(format!("Image {} is missing from uploads/", url));
+        }
+    }
+
+    Ok(problems)
+}
+
+/// Uploaded files nothing refers to, with their sizes. Resized copies and
+/// other formats count as referenced along with their original. Files
+/// younger than `min_age` are left out: they may belong to an item that is
+/// still being created.
+pub async fn orphaned_images(
+    pool: &SqlitePool,
+    min_age: Duration,
+) -> Result<Vec<(String, u64)>, Error> {
+    let referenced_urls = referenced_images(pool).await?;
+    let referenced: HashSet<&str> = referenced_urls.iter().map(|url| upload_key(url)).collect();
+    let now = SystemTime::now();
+
+    let mut orphans = Vec::new();
+    for (url, metadata) in uploaded_files().await? {
+        let age = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .unwrap_or_default();
+        if !referenced.contains(upload_key(&url)) && age >= min_age {
+            orphans.push((url, metadata.len()));
+        }
+    }
+    orphans.sort();
+
+    Ok(orphans)
+}
+
+/// Uploads missing one of their resized copies, such as those saved before
+/// variants were introduced. With `all`, every upload.
+pub async fn missing_variants(all: bool) -> Result<Vec<String>, Error> {
+    let files = uploaded_files().await?;
+    let on_disk: HashSet<&str> = files.iter().map(|(url, _)| url.as_str()).collect();
+
+    let mut missing = Vec::new();
+    for (url, _) in &files {
+        if !is_original(url) {
+            continue;
+        }
+        let Some(variants) = image_variants(url) else {
+            continue;
+        };
+        if all
+            || !on_disk.contains(variants.thumbnail.as_str())
+            || !on_disk.contains(variants.card.as_str())
+        {
+            missing.push(url.clone());
+        }
+    }
+    missing.sort();
+
+    Ok(missing)
+}
@@ -0,0 +1,318 @@
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use sqlx::SqlitePool;
+
+use crate::models::{
+    AddCategoryMember, CategoryMember, CategoryRole, Claims, UpdateCategoryMember,
+};
+
+/// Ids of the categories a user can see: their own and those shared with
+/// them. For `category_id IN (...)`; binds the user id twice.
+pub(crate) const VISIBLE_CATEGORIES: &str = "SELECT id FROM categories WHERE user_id = ?
+     UNION SELECT category_id FROM category_members WHERE user_id = ?";
+
+/// Like `VISIBLE_CATEGORIES`, limited to those the user may change items in.
+pub(crate) const EDITABLE_CATEGORIES: &str = "SELECT id FROM categories WHERE user_id = ?
+     UNION SELECT category_id FROM category_members WHERE user_id = ? AND role = 'editor'";
+
+/// The user's role in a category, or None if they can't see it.
+pub(crate) async fn category_role(
+    pool: &SqlitePool,
+    category_id: &str,
+    user_id: i64,
+) -> Result<Option<CategoryRole>, sqlx::Error> {
+    sqlx::query_scalar::<_, CategoryRole>(
+        "SELECT CASE WHEN c.user_id = ? THEN 'owner' ELSE m.role END
+         FROM categories c
+         LEFT JOIN category_members m ON m.category_id = c.id AND m.user_id = ?
+         WHERE c.id = ? AND (c.user_id = ? OR m.user_id IS NOT NULL)",
+    )
+    .bind(user_id)
+    .bind(user_id)
+    .bind(category_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+}
+
+fn forbidden(needed: CategoryRole) -> (StatusCode, String) {
+    let message = match needed {
+        CategoryRole::Owner => "Only the owner can do that",
+        _ => "You can only view this category",
+    };
+    (StatusCode::FORBIDDEN, message.to_string())
+}
+
+/// Checks the user has at least `needed` rights in a category. Categories
+/// they can't see at all are reported as missing.
+pub(crate) async fn require_role(
+    pool: &SqlitePool,
+    category_id: &str,
+    user_id: i64,
+    needed: CategoryRole,
+) -> Result<CategoryRole, (StatusCode, String)> {
+    let role = category_role(pool, category_id, user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Category not found".to_string()))?;
+
+    if role < needed {
+        return Err(forbidden(needed));
+    }
+    Ok(role)
+}
+
+/// Same as `require_role`, for the category an item is in. Returns the
+/// category's id.
+pub(crate) async fn require_item_role(
+    pool: &SqlitePool,
+    item_id: &str,
+    user_id: i64,
+    needed: CategoryRole,
+) -> Result<String, (StatusCode, String)> {
+    let not_found = || (StatusCode::NOT_FOUND, "Item not found".to_string());
+
+    let category_id = sqlx::query_scalar!("SELECT category_id FROM items WHERE id = ?", item_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(not_found)?;
+
+    let role = category_role(pool, &category_id, user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(not_found)?;
+
+    if role < needed {
+        return Err(forbidden(needed));
+    }
+    Ok(category_id)
+}
+
+/// Resolves a category name the way the API always has. The user's own
+/// category wins over one shared with them that has the same name.
+pub(crate) async fn find_category_by_name(
+    pool: &SqlitePool,
+    name: &str,
+    user_id: i64,
+) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar::<_, String>(
+        "SELECT c.id
+         FROM categories c
+         LEFT JOIN category_members m ON m.category_id = c.id AND m.user_id = ?
+         WHERE c.name = ? AND (c.user_id = ? OR m.user_id IS NOT NULL)
+         ORDER BY c.user_id = ? DESC, c.created_at
+         LIMIT 1",
+    )
+    .bind(user_id)
+    .bind(name)
+    .bind(user_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Like `find_category_by_name`, passing over shared categories the user can
+/// only view.
+pub(crate) async fn find_editable_category_by_name(
+    pool: &SqlitePool,
+    name: &str,
+    user_id: i64,
+) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar::<_, String>(
+        "SELECT c.id
+         FROM categories c
+         LEFT JOIN category_members m ON m.category_id = c.id AND m.user_id = ?
+         WHERE c.name = ? AND (c.user_id = ? OR m.role = 'editor')
+         ORDER BY c.user_id = ? DESC, c.created_at
+         LIMIT 1",
+    )
+    .bind(user_id)
+    .bind(name)
+    .bind(user_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+}
+
+async fn fetch_member(
+    pool: &SqlitePool,
+    category_id: &str,
+    user_id: i64,
+) -> Result<Option<CategoryMember>, sqlx::Error> {
+    sqlx::query_as::<_, CategoryMember>(
+        "SELECT m.user_id, u.username, m.role
+         FROM category_members m
+         JOIN users u ON m.user_id = u.id
+         WHERE m.category_id = ? AND m.user_id = ?",
+    )
+    .bind(category_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// The owner followed by everyone the category is shared with.
+pub async fn get_members(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<CategoryMember>>, (StatusCode, String)> {
+    require_role(&pool, &id, claims.uid, CategoryRole::Viewer).await?;
+
+    let members = sqlx::query_as::<_, CategoryMember>(
+        "SELECT user_id, username, role FROM (
+             SELECT u.id as user_id, u.username, 'owner' as role, 0 as is_member
+             FROM categories c
+             JOIN users u ON c.user_id = u.id
+             WHERE c.id = ?
+             UNION ALL
+             SELECT m.user_id, u.username, m.role, 1 as is_member
+             FROM category_members m
+             JOIN users u ON m.user_id = u.id
+             WHERE m.category_id = ?
+         )
+         ORDER BY is_member, username",
+    )
+    .bind(&id)
+    .bind(&id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(members))
+}
+
+pub async fn add_member(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    Json(payload): Json<AddCategoryMember>,
+) -> Result<Json<CategoryMember>, (StatusCode, String)> {
+    require_role(&pool, &id, claims.uid, CategoryRole::Owner).await?;
+
+    if payload.role == CategoryRole::Owner {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Members can be editors or viewers".to_string(),
+        ));
+    }
+
+    let user_id = sqlx::query_scalar!(
+        r#"SELECT id as "id!" FROM users WHERE username = ? COLLATE NOCASE"#,
+        payload.username
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, "User not found".to_string()))?;
+
+    if category_role(&pool, &id, user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .is_some()
+    {
+        return Err((StatusCode::CONFLICT, "Already a member".to_string()));
+    }
+
+    sqlx::query("INSERT INTO category_members (category_id, user_id, role) VALUES (?, ?, ?)")
+        .bind(&id)
+        .bind(user_id)
+        .bind(payload.role)
+        .execute(&pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let member = fetch_member(&pool, &id, user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Member not found".to_string()))?;
+
+    Ok(Json(member))
+}
+
+pub async fn update_member(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path((id, user_id)): Path<(String, i64)>,
+    Json(payload): Json<UpdateCategoryMember>,
+) -> Result<Json<CategoryMember>, (StatusCode, String)> {
+    require_role(&pool, &id, claims.uid, CategoryRole::Owner).await?;
+
+    if payload.role == CategoryRole::Owner {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Members can be editors or viewers".to_string(),
+        ));
+    }
+
+    let result =
+        sqlx::query("UPDATE category_members SET role = ? WHERE category_id = ? AND user_id = ?")
+            .bind(payload.role)
+            .bind(&id)
+            .bind(user_id)
+            .execute(&pool)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Member not found".to_string()));
+    }
+
+    let member = fetch_member(&pool, &id, user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Member not found".to_string()))?;
+
+    Ok(Json(member))
+}
+
+/// The owner can remove anyone; members can remove themselves. Items the
+/// member added stay in the category and pass to the owner.
+pub async fn remove_member(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path((id, user_id)): Path<(String, i64)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if user_id != claims.uid {
+        require_role(&pool, &id, claims.uid, CategoryRole::Owner).await?;
+    }
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let result = sqlx::query!(
+        "DELETE FROM category_members WHERE category_id = ? AND user_id = ?",
+        id,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Member not found".to_string()));
+    }
+
+    sqlx::query!(
+        "UPDATE items SET user_id = (SELECT user_id FROM categories WHERE id = ?)
+         WHERE category_id = ? AND user_id = ?",
+        id,
+        id,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -2,9 +2,12 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
+use crate::upload::image_variants;
+
 #[derive(Debug, FromRow, Clone)]
 pub struct DbItem {
     pub id: String,
+    pub category_id: String,
     pub category: String, // Flattened for API: Name of the category
     pub name: String,
     pub notes: Option<String>,
@@ -13,45 +16,68 @@ pub struct DbItem {
     pub rank_order: Option<f64>,
 }
 
+/// URLs of an uploaded image at each size. Uploads from before variants
+/// existed only have `full` until `stuff-admin image-variants` has run.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImageVariants {
+    // At most 160px on the longest side
+    pub thumbnail: String,
+    // At most 640px
+    pub card: String,
+    pub full: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ApiItem {
     pub id: String,
+    // Tells apart an own category and a shared one with the same name
+    pub category_id: String,
     pub category: String,
     pub name: String,
     pub notes: Option<String>,
     pub image_url: Option<String>,
+    // Smaller copies of image_url, for uploaded images only
+    pub image_variants: Option<ImageVariants>,
     pub created_at: DateTime<Utc>,
     pub rank_order: Option<f64>,
     pub normalized_score: Option<f64>,
+    // Only set when the score is estimated from comparisons
+    pub score_interval: Option<ScoreInterval>,
+}
+
+/// 95% confidence bounds around `normalized_score`, on the same 0-100 scale.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct ScoreInterval {
+    pub low: f64,
+    pub high: f64,
 }
 
+/// Scores depend on the rest of the item's category, so they are left empty
+/// here and filled in by `scoring::score_items`.
 impl From<DbItem> for ApiItem {
     fn from(item: DbItem) -> Self {
-        // Sigmoid mapping: 0-100 based on rank_order
-        // rank_order 0 -> ~50
-        // rank_order 300 -> ~73
-        // rank_order -300 -> ~26
-        // Scale factor 300.0 chosen to give reasonable spread
-        let score = item
-            .rank_order
-            .map(|rank| 100.0 / (1.0 + (-rank / 300.0).exp()));
-
         Self {
             id: item.id,
+            category_id: item.category_id,
             category: item.category,
             name: item.name,
             notes: item.notes,
+            image_variants: item.image_url.as_deref().and_then(image_variants),
             image_url: item.image_url,
             created_at: item.created_at,
             rank_order: item.rank_order,
-            normalized_score: score,
+            normalized_score: None,
+            score_interval: None,
         }
     }
 }
 
+/// `category` is a name: the caller's own category of that name, else one
+/// shared with them, else a new one. `category_id` picks one directly.
 #[derive(Debug, Deserialize)]
 pub struct CreateItem {
     pub category: String,
+    pub category_id: Option<String>,
     pub name: String,
     pub notes: Option<String>,
     pub image_url: Option<String>,
@@ -60,17 +86,212 @@ pub struct CreateItem {
 #[derive(Debug, Deserialize)]
 pub struct UpdateItem {
     pub category: Option<String>,
+    pub category_id: Option<String>,
     pub name: Option<String>,
     pub notes: Option<String>,
     pub image_url: Option<String>,
     pub rank_order: Option<f64>,
 }
 
-#[derive(Debug, Serialize, FromRow)]
+#[derive(Debug, FromRow, Clone)]
+pub struct RankSession {
+    pub id: String,
+    pub item_id: String,
+    pub upper_rank: Option<f64>,
+    pub lower_rank: Option<f64>,
+    pub comparison_item_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StartRankSession {
+    pub item_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RankChoice {
+    /// The item being ranked wins the comparison
+    Target,
+    /// The item it was compared against wins
+    Comparison,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RankAnswer {
+    pub prefer: RankChoice,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RankSessionResponse {
+    pub id: String,
+    pub item: ApiItem,
+    // None once the session is complete and the rank has been saved
+    pub comparison: Option<ApiItem>,
+    pub complete: bool,
+}
+
+/// A single recorded ranking decision: `winner` was preferred over `loser`.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Comparison {
+    pub id: String,
+    pub category: String,
+    pub winner_id: String,
+    pub winner_name: String,
+    pub loser_id: String,
+    pub loser_name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RebalanceResponse {
+    pub category_id: String,
+    pub renumbered: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+pub enum ScoringMode {
+    /// Percentile of the item's rank_order within the category
+    Rank,
+    /// Bradley-Terry strengths fitted to the category's comparisons
+    BradleyTerry,
+}
+
+/// What a user may do with a category, weakest first. The owner is
+/// `categories.user_id`; everyone else is in `category_members`.
+#[derive(
+    Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, sqlx::Type,
+)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+pub enum CategoryRole {
+    /// Sees the category and its items
+    Viewer,
+    /// Also adds, edits, deletes and ranks items
+    Editor,
+    /// Also changes the category itself, its sharing and its members
+    Owner,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct CategoryMember {
+    pub user_id: i64,
+    pub username: String,
+    pub role: CategoryRole,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AddCategoryMember {
+    pub username: String,
+    pub role: CategoryRole,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateCategoryMember {
+    pub role: CategoryRole,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct Category {
     pub id: String,
     pub name: String,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    pub cover_image_url: Option<String>,
+    pub position: Option<i64>,
+    pub scoring_mode: ScoringMode,
+    pub is_public: bool,
+    pub share_slug: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub role: CategoryRole,
+}
+
+#[derive(Debug, FromRow)]
+pub struct DbCategorySummary {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    pub cover_image_url: Option<String>,
+    pub position: Option<i64>,
+    pub scoring_mode: ScoringMode,
+    pub is_public: bool,
+    pub share_slug: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub item_count: i64,
+    pub ranked_count: i64,
+    pub role: CategoryRole,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CategorySummary {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    pub cover_image_url: Option<String>,
+    pub position: Option<i64>,
+    pub scoring_mode: ScoringMode,
+    pub is_public: bool,
+    pub share_slug: Option<String>,
     pub created_at: DateTime<Utc>,
+    // Most recent change to any item in the category
+    pub updated_at: DateTime<Utc>,
+    pub item_count: i64,
+    pub ranked_count: i64,
+    pub unranked_count: i64,
+    pub top_item: Option<ApiItem>,
+    pub role: CategoryRole,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateCategory {
+    pub name: String,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    pub cover_image_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateCategory {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    pub cover_image_url: Option<String>,
+    // Personal to each member; everything else is for the owner only
+    pub position: Option<i64>,
+    // Publishing creates a share_slug; unpublishing revokes it
+    pub is_public: Option<bool>,
+    pub scoring_mode: Option<ScoringMode>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReorderCategories {
+    /// Category ids in the order they should be listed
+    pub ids: Vec<String>,
+}
+
+/// Read-only view of a public category, safe to show to anyone with the link.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PublicCategory {
+    pub name: String,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    pub cover_image_url: Option<String>,
+    pub items: Vec<PublicItem>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PublicItem {
+    /// 1-based position in the ranking
+    pub rank: usize,
+    pub name: String,
+    pub notes: Option<String>,
+    pub image_url: Option<String>,
+    pub normalized_score: Option<f64>,
+    pub score_interval: Option<ScoreInterval>,
 }
 
 #[derive(Clone, Debug, FromRow, Serialize, Deserialize)]
@@ -78,17 +299,89 @@ pub struct User {
     pub id: i64,
     pub username: String,
     pub password_hash: String,
+    pub token_version: i64,
+    pub totp_enabled: bool,
+    pub is_admin: bool,
+    pub disabled_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Account {
+    pub id: i64,
+    pub username: String,
+    pub totp_enabled: bool,
+    pub is_admin: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateAccount {
+    pub username: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangePassword {
+    pub current_password: String,
+    pub new_password: String,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct LoginRequest {
     pub username: String,
     pub password: String,
+    /// Required when two-factor authentication is on, unless a recovery code is given
+    pub totp_code: Option<String>,
+    pub recovery_code: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TotpEnrollment {
+    /// Base32 secret for apps that can't scan the URI
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfirmTotp {
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfirmPassword {
+    pub current_password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecoveryCodes {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterRequest {
+    pub username: String,
+    pub password: String,
+    pub invite_code: String,
 }
 
 #[derive(Debug, Serialize)]
 pub struct LoginResponse {
     pub token: String,
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct SessionInfo {
+    pub id: String,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// Whether this is the session making the request
+    pub current: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -96,4 +389,148 @@ pub struct Claims {
     pub sub: String,
     pub uid: i64,
     pub exp: usize,
+    /// `users.token_version` at the time the token was issued
+    #[serde(default)]
+    pub ver: i64,
+    /// Session the token was issued for; None for personal access tokens
+    pub sid: Option<String>,
+    #[serde(default)]
+    pub admin: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+pub enum TokenScope {
+    /// GET requests only
+    #[default]
+    Read,
+    ReadWrite,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateAccessToken {
+    pub name: String,
+    #[serde(default)]
+    pub scope: TokenScope,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct AccessToken {
+    pub id: String,
+    pub name: String,
+    pub scope: TokenScope,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// Returned once on creation; the plain token can't be retrieved again.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatedAccessToken {
+    #[serde(flatten)]
+    pub info: AccessToken,
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct AdminUser {
+    pub id: i64,
+    pub username: String,
+    pub is_admin: bool,
+    pub disabled_at: Option<DateTime<Utc>>,
+    pub totp_enabled: bool,
+    pub item_count: i64,
+    pub category_count: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateUser {
+    pub username: String,
+    pub password: String,
+    #[serde(default)]
+    pub is_admin: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateUser {
+    pub is_admin: Option<bool>,
+    pub disabled: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResetPassword {
+    pub new_password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct InviteCode {
+    pub code: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StorageUsage {
+    /// Everything under uploads/, including files no longer referenced
+    pub total_bytes: u64,
+    pub users: Vec<UserStorage>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserStorage {
+    pub id: i64,
+    pub username: String,
+    pub image_count: u64,
+    pub bytes: u64,
+}
+
+/// A user's categories, items and comparison history, as written by
+/// `stuff-admin export`. Images are referenced by URL, not embedded.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Export {
+    pub version: u32,
+    pub categories: Vec<ExportCategory>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ExportCategory {
+    #[serde(skip)]
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    pub cover_image_url: Option<String>,
+    pub position: Option<i64>,
+    pub scoring_mode: ScoringMode,
+    pub created_at: DateTime<Utc>,
+    #[sqlx(skip)]
+    pub items: Vec<ExportItem>,
+    #[sqlx(skip)]
+    pub comparisons: Vec<ExportComparison>,
+}
+
+/// `id` is only used to tie comparisons to items; imports get new ids.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ExportItem {
+    pub id: String,
+    pub name: String,
+    pub notes: Option<String>,
+    pub image_url: Option<String>,
+    pub rank_order: Option<f64>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ExportComparison {
+    pub winner_id: String,
+    pub loser_id: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct CategoryCount {
+    pub username: String,
+    pub name: String,
+    pub item_count: i64,
+    pub ranked_count: i64,
 }
@@ -0,0 +1,546 @@
+use crate::members::{EDITABLE_CATEGORIES, VISIBLE_CATEGORIES, require_item_role, require_role};
+use crate::models::{
+    ApiItem, CategoryRole, Claims, Comparison, DbItem, RankAnswer, RankChoice, RankSession,
+    RankSessionResponse, RebalanceResponse, StartRankSession,
+};
+use crate::scoring::{score_item, score_items};
+use axum::{
+    Json,
+    extract::{Extension, Path, State},
+    http::StatusCode,
+};
+use sqlx::{SqliteConnection, SqlitePool};
+use uuid::Uuid;
+
+// Rank given to the first ranked item in a category
+const FIRST_RANK: f64 = 100.0;
+// Distance kept from the current best/worst item when inserting at either end
+const EDGE_STEP: f64 = 100.0;
+// Neighbours closer than this have run out of room for midpoint insertion,
+// so the category gets spread back out
+const MIN_RANK_GAP: f64 = 1e-3;
+
+/// Rank for an item that belongs strictly between `upper` and `lower`.
+/// `None` means there is no neighbour on that side.
+fn insertion_rank(upper: Option<f64>, lower: Option<f64>) -> f64 {
+    match (upper, lower) {
+        (None, None) => FIRST_RANK,
+        (Some(upper), None) => upper - EDGE_STEP,
+        (None, Some(lower)) => lower + EDGE_STEP,
+        (Some(upper), Some(lower)) => (upper + lower) / 2.0,
+    }
+}
+
+/// Spreads a category's ranked items back out to evenly spaced ranks,
+/// keeping their order. Unless `force` is set this only happens once two
+/// neighbours are closer than `MIN_RANK_GAP`. Open ranking sessions in the
+/// category are moved along so their windows keep the same items. Returns how
+/// many items were renumbered.
+pub async fn renumber_category(
+    conn: &mut SqliteConnection,
+    category_id: &str,
+    force: bool,
+) -> Result<u64, sqlx::Error> {
+    let ranked = sqlx::query_as::<_, (String, f64)>(
+        "SELECT id, rank_order FROM items
+         WHERE category_id = ? AND rank_order IS NOT NULL
+         ORDER BY rank_order DESC, created_at ASC, id ASC",
+    )
+    .bind(category_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let crowded = ranked
+        .windows(2)
+        .any(|pair| pair[0].1 - pair[1].1 < MIN_RANK_GAP);
+
+    if !force && !crowded {
+        return Ok(0);
+    }
+
+    let count = ranked.len();
+    let mut moved = Vec::with_capacity(count);
+    for (idx, (id, old_rank)) in ranked.iter().enumerate() {
+        let rank = (count - idx) as f64 * EDGE_STEP;
+        sqlx::query!("UPDATE items SET rank_order = ? WHERE id = ?", rank, id)
+            .execute(&mut *conn)
+            .await?;
+        moved.push((*old_rank, rank));
+    }
+
+    // A window holds the items strictly between its bounds. The new upper
+    // bound is the lowest new rank of anything at or above the old one, and
+    // likewise below, so ties and since-deleted bounding items keep the same
+    // items inside.
+    let sessions = sqlx::query_as::<_, (String, Option<f64>, Option<f64>)>(
+        "SELECT s.id, s.upper_rank, s.lower_rank
+         FROM rank_sessions s
+         JOIN items i ON s.item_id = i.id
+         WHERE i.category_id = ?",
+    )
+    .bind(category_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for (id, upper, lower) in sessions {
+        let upper = upper.and_then(|upper| {
+            moved
+                .iter()
+                .filter(|(old, _)| *old >= upper)
+                .map(|(_, new)| *new)
+                .reduce(f64::min)
+        });
+        let lower = lower.and_then(|lower| {
+            moved
+                .iter()
+                .filter(|(old, _)| *old <= lower)
+                .map(|(_, new)| *new)
+                .reduce(f64::max)
+        });
+        sqlx::query!(
+            "UPDATE rank_sessions SET upper_rank = ?, lower_rank = ? WHERE id = ?",
+            upper,
+            lower,
+            id
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(count as u64)
+}
+
+/// Renumbers the category an item lives in if its ranks have collapsed.
+pub async fn rebalance_item_category(pool: &SqlitePool, item_id: &str) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let rec = sqlx::query!("SELECT category_id FROM items WHERE id = ?", item_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+    if let Some(rec) = rec {
+        renumber_category(&mut tx, &rec.category_id, false).await?;
+    }
+
+    tx.commit().await
+}
+
+async fn fetch_item(
+    pool: &SqlitePool,
+    id: &str,
+    user_id: i64,
+) -> Result<Option<DbItem>, sqlx::Error> {
+    sqlx::query_as::<_, DbItem>(&format!(
+        "SELECT i.id, i.category_id, i.name, i.notes, i.image_url, i.created_at,
+                i.rank_order,
+                c.name as category
+         FROM items i
+         JOIN categories c ON i.category_id = c.id
+         WHERE i.id = ? AND i.category_id IN ({VISIBLE_CATEGORIES})"
+    ))
+    .bind(id)
+    .bind(user_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+}
+
+async fn fetch_session(
+    pool: &SqlitePool,
+    id: &str,
+    user_id: i64,
+) -> Result<Option<RankSession>, sqlx::Error> {
+    // Losing edit rights to the category ends access to the session too
+    sqlx::query_as::<_, RankSession>(&format!(
+        "SELECT s.id, s.item_id, s.upper_rank, s.lower_rank, s.comparison_item_id
+         FROM rank_sessions s
+         JOIN items i ON s.item_id = i.id
+         WHERE s.id = ? AND s.user_id = ? AND i.category_id IN ({EDITABLE_CATEGORIES})"
+    ))
+    .bind(id)
+    .bind(user_id)
+    .bind(user_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Picks the middle of the ranked items still inside the session's window,
+/// or `None` once the window is empty and the item's position is known.
+async fn next_comparison(
+    pool: &SqlitePool,
+    item: &DbItem,
+    session: &RankSession,
+) -> Result<Option<DbItem>, sqlx::Error> {
+    let candidates = sqlx::query_as::<_, DbItem>(
+        "SELECT i.id, i.category_id, i.name, i.notes, i.image_url, i.created_at,
+                i.rank_order,
+                c.name as category
+         FROM items i
+         JOIN categories c ON i.category_id = c.id
+         WHERE i.category_id = ? AND i.id != ?
+           AND i.rank_order IS NOT NULL
+           AND (? IS NULL OR i.rank_order < ?)
+           AND (? IS NULL OR i.rank_order > ?)
+         ORDER BY i.rank_order DESC",
+    )
+    .bind(&item.category_id)
+    .bind(&item.id)
+    .bind(session.upper_rank)
+    .bind(session.upper_rank)
+    .bind(session.lower_rank)
+    .bind(session.lower_rank)
+    .fetch_all(pool)
+    .await?;
+
+    let mid = candidates.len() / 2;
+    Ok(candidates.into_iter().nth(mid))
+}
+
+/// Response for a session that is still waiting on `comparison`.
+async fn pending_response(
+    pool: &SqlitePool,
+    session_id: String,
+    item: DbItem,
+    comparison: DbItem,
+) -> Result<RankSessionResponse, sqlx::Error> {
+    let mut items = [ApiItem::from(item), ApiItem::from(comparison)];
+    score_items(pool, &mut items).await?;
+    let [item, comparison] = items;
+
+    Ok(RankSessionResponse {
+        id: session_id,
+        item,
+        comparison: Some(comparison),
+        complete: false,
+    })
+}
+
+/// Moves the session to its next comparison, or commits the final rank and
+/// closes the session when there is nothing left to compare against.
+async fn advance(
+    pool: &SqlitePool,
+    session: RankSession,
+    user_id: i64,
+) -> Result<RankSessionResponse, (StatusCode, String)> {
+    let item = fetch_item(pool, &session.item_id, user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Item not found".to_string()))?;
+
+    let comparison = next_comparison(pool, &item, &session)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if let Some(comparison) = comparison {
+        sqlx::query!(
+            "UPDATE rank_sessions SET comparison_item_id = ? WHERE id = ?",
+            comparison.id,
+            session.id
+        )
+        .execute(pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        return pending_response(pool, session.id, item, comparison)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+    }
+
+    let rank = insertion_rank(session.upper_rank, session.lower_rank);
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    sqlx::query!(
+        "UPDATE items SET rank_order = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        rank,
+        item.id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    sqlx::query!("DELETE FROM rank_sessions WHERE id = ?", session.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    rebalance_item_category(pool, &item.id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // Re-read so the response reflects any renumbering
+    let item = fetch_item(pool, &item.id, user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Item not found".to_string()))?;
+
+    let item = score_item(pool, item)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(RankSessionResponse {
+        id: session.id,
+        item,
+        comparison: None,
+        complete: true,
+    })
+}
+
+pub async fn start_rank_session(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<StartRankSession>,
+) -> Result<Json<RankSessionResponse>, (StatusCode, String)> {
+    require_item_role(&pool, &payload.item_id, claims.uid, CategoryRole::Editor).await?;
+
+    // Resume the item's unfinished session if there is one, taking it over
+    // when another member of a shared category started it
+    let existing = sqlx::query_as::<_, RankSession>(
+        "SELECT id, item_id, upper_rank, lower_rank, comparison_item_id
+         FROM rank_sessions
+         WHERE item_id = ?",
+    )
+    .bind(&payload.item_id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let session = match existing {
+        Some(session) => {
+            sqlx::query!(
+                "UPDATE rank_sessions SET user_id = ? WHERE id = ?",
+                claims.uid,
+                session.id
+            )
+            .execute(&pool)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+            session
+        }
+        None => {
+            let session_id = Uuid::new_v4().to_string();
+            sqlx::query!(
+                "INSERT INTO rank_sessions (id, item_id, user_id) VALUES (?, ?, ?)",
+                session_id,
+                payload.item_id,
+                claims.uid
+            )
+            .execute(&pool)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+            RankSession {
+                id: session_id,
+                item_id: payload.item_id,
+                upper_rank: None,
+                lower_rank: None,
+                comparison_item_id: None,
+            }
+        }
+    };
+
+    Ok(Json(advance(&pool, session, claims.uid).await?))
+}
+
+pub async fn get_rank_session(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Json<RankSessionResponse>, (StatusCode, String)> {
+    let session = fetch_session(&pool, &id, claims.uid)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            "Ranking session not found".to_string(),
+        ))?;
+
+    let item = fetch_item(&pool, &session.item_id, claims.uid)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Item not found".to_string()))?;
+
+    let comparison = match &session.comparison_item_id {
+        Some(comparison_id) => fetch_item(&pool, comparison_id, claims.uid)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?,
+        None => None,
+    };
+
+    match comparison {
+        Some(comparison) => pending_response(&pool, session.id, item, comparison)
+            .await
+            .map(Json)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+        // The pending comparison was deleted in the meantime, pick a new one
+        None => Ok(Json(advance(&pool, session, claims.uid).await?)),
+    }
+}
+
+pub async fn answer_rank_session(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    Json(payload): Json<RankAnswer>,
+) -> Result<Json<RankSessionResponse>, (StatusCode, String)> {
+    let mut session = fetch_session(&pool, &id, claims.uid)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            "Ranking session not found".to_string(),
+        ))?;
+
+    let comparison_id = session
+        .comparison_item_id
+        .clone()
+        .ok_or((StatusCode::CONFLICT, "No comparison is pending".to_string()))?;
+
+    let comparison_rank = sqlx::query!(
+        "SELECT rank_order FROM items
+         WHERE id = ? AND category_id = (SELECT category_id FROM items WHERE id = ?)",
+        comparison_id,
+        session.item_id
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .and_then(|rec| rec.rank_order);
+
+    // If the comparison item was deleted or unranked since it was handed out,
+    // the answer tells us nothing; just move on to a fresh comparison.
+    if let Some(rank) = comparison_rank {
+        match payload.prefer {
+            RankChoice::Target => session.lower_rank = Some(rank),
+            RankChoice::Comparison => session.upper_rank = Some(rank),
+        }
+
+        let (winner_id, loser_id) = match payload.prefer {
+            RankChoice::Target => (&session.item_id, &comparison_id),
+            RankChoice::Comparison => (&comparison_id, &session.item_id),
+        };
+
+        let mut tx = pool
+            .begin()
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        sqlx::query!(
+            "UPDATE rank_sessions SET upper_rank = ?, lower_rank = ? WHERE id = ?",
+            session.upper_rank,
+            session.lower_rank,
+            session.id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        let comparison_record_id = Uuid::new_v4().to_string();
+        sqlx::query!(
+            "INSERT INTO comparisons (id, user_id, category_id, winner_id, loser_id)
+             SELECT ?, ?, category_id, ?, ? FROM items WHERE id = ?",
+            comparison_record_id,
+            claims.uid,
+            winner_id,
+            loser_id,
+            session.item_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+
+    Ok(Json(advance(&pool, session, claims.uid).await?))
+}
+
+pub async fn delete_rank_session(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let result = sqlx::query!(
+        "DELETE FROM rank_sessions WHERE id = ? AND user_id = ?",
+        id,
+        claims.uid
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if result.rows_affected() == 0 {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Ranking session not found".to_string(),
+        ));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn rebalance_category(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(category_id): Path<String>,
+) -> Result<Json<RebalanceResponse>, (StatusCode, String)> {
+    require_role(&pool, &category_id, claims.uid, CategoryRole::Editor).await?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let renumbered = renumber_category(&mut tx, &category_id, true)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(RebalanceResponse {
+        category_id,
+        renumbered,
+    }))
+}
+
+pub async fn get_item_comparisons(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<Comparison>>, (StatusCode, String)> {
+    require_item_role(&pool, &id, claims.uid, CategoryRole::Viewer).await?;
+
+    // Includes decisions made by every member of a shared category
+    let comparisons = sqlx::query_as::<_, Comparison>(
+        "SELECT cmp.id, c.name as category,
+                cmp.winner_id, w.name as winner_name,
+                cmp.loser_id, l.name as loser_name,
+                cmp.created_at
+         FROM comparisons cmp
+         JOIN categories c ON cmp.category_id = c.id
+         JOIN items w ON cmp.winner_id = w.id
+         JOIN items l ON cmp.loser_id = l.id
+         WHERE cmp.winner_id = ? OR cmp.loser_id = ?
+         ORDER BY cmp.created_at, cmp.rowid",
+    )
+    .bind(&id)
+    .bind(&id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(comparisons))
+}
@@ -0,0 +1,217 @@
+use crate::models::{ApiItem, DbItem, ScoreInterval, ScoringMode};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+// Virtual games every item plays against an average opponent (half won, half
+// lost). Keeps strengths finite for items that have only ever won or lost.
+const PRIOR_GAMES: f64 = 2.0;
+const MAX_ITERATIONS: usize = 500;
+const TOLERANCE: f64 = 1e-9;
+// Two-sided 95% normal quantile
+const Z_95: f64 = 1.96;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Strength {
+    pub score: f64,
+    pub interval: ScoreInterval,
+}
+
+// Probability (0-100) of beating an average item given a log-strength
+fn to_score(log_strength: f64) -> f64 {
+    100.0 / (1.0 + (-log_strength).exp())
+}
+
+/// Fits a Bradley-Terry model to `comparisons`, given as (winner, loser)
+/// indexes into a list of `count` items, using Hunter's MM algorithm.
+///
+/// Each score is the estimated chance (0-100) of the item beating an average
+/// one, with a 95% interval from the diagonal of the Fisher information.
+pub fn bradley_terry(count: usize, comparisons: &[(usize, usize)]) -> Vec<Strength> {
+    let mut wins = vec![PRIOR_GAMES / 2.0; count];
+    let mut pairs: Vec<(usize, usize)> = comparisons
+        .iter()
+        .map(|&(winner, loser)| {
+            wins[winner] += 1.0;
+            (winner.min(loser), winner.max(loser))
+        })
+        .collect();
+    pairs.sort_unstable();
+
+    // Each item's opponents with the number of games against them. Most pairs
+    // never meet, so every round only visits the ones that did.
+    let mut opponents: Vec<Vec<(usize, f64)>> = vec![Vec::new(); count];
+    for games in pairs.chunk_by(|a, b| a == b) {
+        let (i, j) = games[0];
+        let played = games.len() as f64;
+        opponents[i].push((j, played));
+        opponents[j].push((i, played));
+    }
+
+    // The average opponent has strength 1, which also pins down the scale
+    let mut strength = vec![1.0; count];
+    for _ in 0..MAX_ITERATIONS {
+        let next: Vec<f64> = (0..count)
+            .map(|i| {
+                let mut denominator = PRIOR_GAMES / (strength[i] + 1.0);
+                for &(j, played) in &opponents[i] {
+                    denominator += played / (strength[i] + strength[j]);
+                }
+                wins[i] / denominator
+            })
+            .collect();
+
+        let change = next
+            .iter()
+            .zip(&strength)
+            .map(|(new, old)| (new.ln() - old.ln()).abs())
+            .fold(0.0, f64::max);
+        strength = next;
+
+        if change < TOLERANCE {
+            break;
+        }
+    }
+
+    (0..count)
+        .map(|i| {
+            let vs_average = strength[i] / (strength[i] + 1.0);
+            let mut information = PRIOR_GAMES * vs_average * (1.0 - vs_average);
+            for &(j, played) in &opponents[i] {
+                let p = strength[i] / (strength[i] + strength[j]);
+                information += played * p * (1.0 - p);
+            }
+
+            let log_strength = strength[i].ln();
+            let margin = Z_95 / information.sqrt();
+            Strength {
+                score: to_score(log_strength),
+                interval: ScoreInterval {
+                    low: to_score(log_strength - margin),
+                    high: to_score(log_strength + margin),
+                },
+            }
+        })
+        .collect()
+}
+
+/// Mid-rank percentile (0-100) of `rank` among `ranks`: the share of items
+/// ranked below it, counting ties as half.
+pub fn percentile(rank: f64, ranks: &[f64]) -> f64 {
+    let below = ranks.iter().filter(|&&r| r < rank).count() as f64;
+    let tied = ranks.iter().filter(|&&r| r == rank).count() as f64;
+    100.0 * (below + tied / 2.0) / ranks.len().max(1) as f64
+}
+
+/// Fills in `normalized_score` for `items` relative to the rest of their
+/// category, using whichever scoring mode the category is set to.
+pub async fn score_items(pool: &SqlitePool, items: &mut [ApiItem]) -> Result<(), sqlx::Error> {
+    let mut category_ids: Vec<String> = items.iter().map(|i| i.category_id.clone()).collect();
+    category_ids.sort();
+    category_ids.dedup();
+
+    for category_id in category_ids {
+        let scoring_mode = sqlx::query_scalar::<_, ScoringMode>(
+            "SELECT scoring_mode FROM categories WHERE id = ?",
+        )
+        .bind(&category_id)
+        .fetch_one(pool)
+        .await?;
+
+        match scoring_mode {
+            ScoringMode::Rank => score_by_rank(pool, &category_id, items).await?,
+            ScoringMode::BradleyTerry => score_by_comparisons(pool, &category_id, items).await?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Converts a single row, scoring it against the rest of its category.
+pub async fn score_item(pool: &SqlitePool, item: DbItem) -> Result<ApiItem, sqlx::Error> {
+    let mut api_item = ApiItem::from(item);
+    score_items(pool, std::slice::from_mut(&mut api_item)).await?;
+    Ok(api_item)
+}
+
+async fn score_by_rank(
+    pool: &SqlitePool,
+    category_id: &str,
+    items: &mut [ApiItem],
+) -> Result<(), sqlx::Error> {
+    let ranks = sqlx::query_scalar::<_, f64>(
+        "SELECT rank_order FROM items WHERE category_id = ? AND rank_order IS NOT NULL",
+    )
+    .bind(category_id)
+    .fetch_all(pool)
+    .await?;
+
+    for item in items.iter_mut().filter(|i| i.category_id == category_id) {
+        item.normalized_score = item.rank_order.map(|rank| percentile(rank, &ranks));
+        item.score_interval = None;
+    }
+
+    Ok(())
+}
+
+/// Items that have never been compared are left without a score.
+async fn score_by_comparisons(
+    pool: &SqlitePool,
+    category_id: &str,
+    items: &mut [ApiItem],
+) -> Result<(), sqlx::Error> {
+    let item_ids = sqlx::query_scalar::<_, String>("SELECT id FROM items WHERE category_id = ?")
+        .bind(category_id)
+        .fetch_all(pool)
+        .await?;
+    let index: HashMap<&str, usize> = item_ids
+        .iter()
+        .enumerate()
+        .map(|(idx, id)| (id.as_str(), idx))
+        .collect();
+
+    // Only decisions between items that are still in this category count
+    let decisions = sqlx::query_as::<_, (String, String)>(
+        "SELECT cmp.winner_id, cmp.loser_id
+         FROM comparisons cmp
+         JOIN items w ON cmp.winner_id = w.id
+         JOIN items l ON cmp.loser_id = l.id
+         WHERE w.category_id = ? AND l.category_id = ?",
+    )
+    .bind(category_id)
+    .bind(category_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut compared = vec![false; item_ids.len()];
+    let pairs: Vec<(usize, usize)> = decisions
+        .iter()
+        .filter_map(|(winner, loser)| {
+            let pair = (*index.get(winner.as_str())?, *index.get(loser.as_str())?);
+            compared[pair.0] = true;
+            compared[pair.1] = true;
+            Some(pair)
+        })
+        .collect();
+
+    // Still hundreds of rounds for a big, much compared category; keep it
+    // off the async workers
+    let count = item_ids.len();
+    let strengths = tokio::task::spawn_blocking(move || bradley_terry(count, &pairs))
+        .await
+        .map_err(|e| sqlx::Error::Io(std::io::Error::other(e)))?;
+
+    for item in items.iter_mut().filter(|i| i.category_id == category_id) {
+        match index.get(item.id.as_str()) {
+            Some(&idx) if compared[idx] => {
+                item.normalized_score = Some(strengths[idx].score);
+                item.score_interval = Some(strengths[idx].interval);
+            }
+            _ => {
+                item.normalized_score = None;
+                item.score_interval = None;
+            }
+        }
+    }
+
+    Ok(())
+}
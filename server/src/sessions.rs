@@ -0,0 +1,264 @@
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode, header::USER_AGENT},
+};
+use sqlx::{SqliteConnection, SqlitePool};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::auth::{JwtKeys, hash_token, issue_token, random_token};
+use crate::models::{Claims, LoginResponse, RefreshRequest, SessionInfo};
+
+const REFRESH_SECRET_LEN: usize = 43;
+// A session ends once its refresh token goes unused for this long
+const REFRESH_TOKEN_LIFETIME: &str = "+30 days";
+
+fn invalid_refresh_token() -> (StatusCode, String) {
+    (
+        StatusCode::UNAUTHORIZED,
+        "Invalid or expired refresh token".to_string(),
+    )
+}
+
+/// Account, session and token management is off limits to personal access
+/// tokens, so a leaked one can't be used to mint more or lock the owner out.
+pub(crate) fn require_session(claims: &Claims) -> Result<&str, (StatusCode, String)> {
+    claims.sid.as_deref().ok_or((
+        StatusCode::FORBIDDEN,
+        "Not available with a personal access token".to_string(),
+    ))
+}
+
+pub(crate) fn user_agent(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Refresh tokens are `<session id>.<secret>`, so the session can be found
+/// without searching by hash.
+async fn session_tokens(
+    conn: &mut SqliteConnection,
+    keys: &JwtKeys,
+    user_id: i64,
+    session_id: String,
+    secret: &str,
+) -> Result<LoginResponse, (StatusCode, String)> {
+    let user = sqlx::query!(
+        "SELECT username, token_version, is_admin FROM users WHERE id = ?",
+        user_id
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let refresh_token = format!("{}.{}", session_id, secret);
+    let token = issue_token(
+        keys,
+        user.username,
+        user_id,
+        user.token_version,
+        user.is_admin,
+        session_id,
+    )
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(LoginResponse {
+        token,
+        refresh_token,
+    })
+}
+
+/// Opens a new session for `user_id` and returns its first pair of tokens.
+pub(crate) async fn start_session(
+    conn: &mut SqliteConnection,
+    keys: &JwtKeys,
+    user_id: i64,
+    user_agent: Option<String>,
+) -> Result<LoginResponse, (StatusCode, String)> {
+    sqlx::query!(
+        "DELETE FROM sessions WHERE user_id = ? AND expires_at <= CURRENT_TIMESTAMP",
+        user_id
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let session_id = Uuid::new_v4().to_string();
+    let secret = random_token(REFRESH_SECRET_LEN);
+    let secret_hash = hash_token(&secret);
+
+    sqlx::query!(
+        "INSERT INTO sessions (id, user_id, refresh_token_hash, user_agent, expires_at)
+         VALUES (?, ?, ?, ?, datetime('now', ?))",
+        session_id,
+        user_id,
+        secret_hash,
+        user_agent,
+        REFRESH_TOKEN_LIFETIME
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    session_tokens(conn, keys, user_id, session_id, &secret).await
+}
+
+/// Swaps a refresh token for a new access token and a new refresh token.
+/// Each refresh token works once; presenting a spent one ends the session.
+pub async fn refresh_token(
+    State(pool): State<SqlitePool>,
+    State(keys): State<Arc<JwtKeys>>,
+    Json(payload): Json<RefreshRequest>,
+) -> Result<Json<LoginResponse>, (StatusCode, String)> {
+    let (session_id, secret) = payload
+        .refresh_token
+        .split_once('.')
+        .ok_or_else(invalid_refresh_token)?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // A disabled account's sessions can't be renewed, even if they still exist
+    let session = sqlx::query!(
+        "SELECT s.user_id, s.refresh_token_hash
+         FROM sessions s
+         JOIN users u ON s.user_id = u.id
+         WHERE s.id = ? AND s.expires_at > CURRENT_TIMESTAMP AND u.disabled_at IS NULL",
+        session_id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or_else(invalid_refresh_token)?;
+
+    if session.refresh_token_hash != hash_token(secret) {
+        // A rotated token being replayed means it leaked, so nobody keeps the session
+        sqlx::query!("DELETE FROM sessions WHERE id = ?", session_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        return Err(invalid_refresh_token());
+    }
+
+    let secret = random_token(REFRESH_SECRET_LEN);
+    let secret_hash = hash_token(&secret);
+
+    sqlx::query!(
+        "UPDATE sessions
+         SET refresh_token_hash = ?, last_used_at = CURRENT_TIMESTAMP,
+             expires_at = datetime('now', ?)
+         WHERE id = ?",
+        secret_hash,
+        REFRESH_TOKEN_LIFETIME,
+        session_id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let tokens = session_tokens(
+        &mut tx,
+        &keys,
+        session.user_id,
+        session_id.to_string(),
+        &secret,
+    )
+    .await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(tokens))
+}
+
+pub async fn logout(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let session_id = require_session(&claims)?;
+
+    sqlx::query!(
+        "DELETE FROM sessions WHERE id = ? AND user_id = ?",
+        session_id,
+        claims.uid
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn get_sessions(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Vec<SessionInfo>>, (StatusCode, String)> {
+    let session_id = require_session(&claims)?;
+
+    let sessions = sqlx::query_as::<_, SessionInfo>(
+        "SELECT id, user_agent, created_at, last_used_at, expires_at, id = ? as current
+         FROM sessions
+         WHERE user_id = ? AND expires_at > CURRENT_TIMESTAMP
+         ORDER BY last_used_at DESC, created_at DESC",
+    )
+    .bind(session_id)
+    .bind(claims.uid)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(sessions))
+}
+
+pub async fn delete_session(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    require_session(&claims)?;
+
+    let result = sqlx::query!(
+        "DELETE FROM sessions WHERE id = ? AND user_id = ?",
+        id,
+        claims.uid
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Session not found".to_string()));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Signs out every device except the one making the request.
+pub async fn delete_other_sessions(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let session_id = require_session(&claims)?;
+
+    sqlx::query!(
+        "DELETE FROM sessions WHERE user_id = ? AND id != ?",
+        claims.uid,
+        session_id
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,50 @@
+use axum::extract::FromRef;
+use sqlx::SqlitePool;
+use std::sync::Arc;
+
+use crate::auth::JwtKeys;
+use crate::upload::UploadSettings;
+
+/// Shared by every handler. Handlers extract the parts they need, e.g.
+/// `State<SqlitePool>`.
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: SqlitePool,
+    pub jwt_keys: Arc<JwtKeys>,
+    pub upload_settings: Arc<UploadSettings>,
+}
+
+impl AppState {
+    pub fn new(pool: SqlitePool, jwt_keys: JwtKeys) -> Self {
+        Self {
+            pool,
+            jwt_keys: Arc::new(jwt_keys),
+            upload_settings: Arc::new(UploadSettings::default()),
+        }
+    }
+
+    pub fn with_upload_settings(self, upload_settings: UploadSettings) -> Self {
+        Self {
+            upload_settings: Arc::new(upload_settings),
+            ..self
+        }
+    }
+}
+
+impl FromRef<AppState> for SqlitePool {
+    fn from_ref(state: &AppState) -> Self {
+        state.pool.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<JwtKeys> {
+    fn from_ref(state: &AppState) -> Self {
+        state.jwt_keys.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<UploadSettings> {
+    fn from_ref(state: &AppState) -> Self {
+        state.upload_settings.clone()
+    }
+}
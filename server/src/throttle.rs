@@ -0,0 +1,121 @@
+use axum::http::HeaderMap;
+use sqlx::SqlitePool;
+use std::net::SocketAddr;
+
+/// How many failures a key gets before it has to wait between attempts, and
+/// how many before it is locked out.
+pub(crate) struct Policy {
+    free_failures: i64,
+    lockout_failures: i64,
+}
+
+pub(crate) const USERNAME_POLICY: Policy = Policy {
+    free_failures: 3,
+    lockout_failures: 10,
+};
+
+// One address may legitimately serve a whole household
+pub(crate) const IP_POLICY: Policy = Policy {
+    free_failures: 10,
+    lockout_failures: 50,
+};
+
+const LOCKOUT_SECS: i64 = 15 * 60;
+// Failures are forgotten after this long without another one
+const FAILURE_WINDOW: &str = "-1 hour";
+
+impl Policy {
+    /// Seconds to wait after the `failures`th failure: doubling once past the
+    /// free attempts, then a fixed lockout.
+    fn delay_secs(&self, failures: i64) -> i64 {
+        if failures >= self.lockout_failures {
+            LOCKOUT_SECS
+        } else if failures >= self.free_failures {
+            2_i64
+                .saturating_pow((failures - self.free_failures) as u32)
+                .min(LOCKOUT_SECS)
+        } else {
+            0
+        }
+    }
+}
+
+pub(crate) fn username_key(username: &str) -> String {
+    format!("user:{}", username.to_lowercase())
+}
+
+pub(crate) fn ip_key(ip: &str) -> String {
+    format!("ip:{}", ip)
+}
+
+/// The client's address. Behind nginx every connection comes from loopback,
+/// so the `X-Real-IP` header it sets is trusted only then.
+pub(crate) fn client_ip(headers: &HeaderMap, peer: Option<SocketAddr>) -> String {
+    let forwarded = headers
+        .get("x-real-ip")
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim().to_string());
+
+    match peer {
+        Some(addr) if !addr.ip().is_loopback() => addr.ip().to_string(),
+        Some(addr) => forwarded.unwrap_or_else(|| addr.ip().to_string()),
+        None => forwarded.unwrap_or_else(|| "unknown".to_string()),
+    }
+}
+
+/// Seconds until any of `keys` may try again, if one is blocked.
+pub(crate) async fn retry_after(
+    pool: &SqlitePool,
+    keys: &[&str],
+) -> Result<Option<i64>, sqlx::Error> {
+    let mut wait = None;
+    for key in keys {
+        let secs = sqlx::query_scalar!(
+            r#"SELECT CAST(strftime('%s', blocked_until) - strftime('%s', 'now') AS INTEGER) as "secs!: i64"
+             FROM login_throttle
+             WHERE key = ? AND blocked_until > datetime('now')"#,
+            key
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        wait = wait.max(secs);
+    }
+    Ok(wait)
+}
+
+pub(crate) async fn record_failure(
+    pool: &SqlitePool,
+    key: &str,
+    policy: &Policy,
+) -> Result<(), sqlx::Error> {
+    let failures = sqlx::query_scalar!(
+        "INSERT INTO login_throttle (key, failures) VALUES (?, 1)
+         ON CONFLICT(key) DO UPDATE SET
+             failures = CASE WHEN last_failure_at < datetime('now', ?) THEN 1 ELSE failures + 1 END,
+             last_failure_at = CURRENT_TIMESTAMP
+         RETURNING failures",
+        key,
+        FAILURE_WINDOW
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let delay = format!("+{} seconds", policy.delay_secs(failures));
+    sqlx::query!(
+        "UPDATE login_throttle SET blocked_until = datetime('now', ?) WHERE key = ?",
+        delay,
+        key
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub(crate) async fn clear(pool: &SqlitePool, key: &str) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM login_throttle WHERE key = ?", key)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::{Method, StatusCode},
+};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+use crate::auth::{hash_token, random_token};
+use crate::models::{AccessToken, Claims, CreateAccessToken, CreatedAccessToken, TokenScope};
+use crate::sessions::require_session;
+
+pub(crate) const ACCESS_TOKEN_PREFIX: &str = "stpat_";
+const ACCESS_TOKEN_SECRET_LEN: usize = 40;
+const MAX_TOKEN_NAME_CHARS: usize = 64;
+
+/// Looks up a personal access token and checks its scope allows `method`.
+pub(crate) async fn access_token_claims(
+    pool: &SqlitePool,
+    token: &str,
+    method: &Method,
+) -> Result<Claims, StatusCode> {
+    let token_hash = hash_token(token);
+    let row = sqlx::query!(
+        r#"UPDATE personal_access_tokens SET last_used_at = CURRENT_TIMESTAMP
+         WHERE token_hash = ?
+         RETURNING user_id, scope as "scope: TokenScope""#,
+        token_hash
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    if row.scope == TokenScope::Read && !matches!(*method, Method::GET | Method::HEAD) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let user = sqlx::query!(
+        "SELECT username, token_version, is_admin FROM users
+         WHERE id = ? AND disabled_at IS NULL",
+        row.user_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    Ok(Claims {
+        sub: user.username,
+        uid: row.user_id,
+        // Access tokens don't expire; they last until revoked
+        exp: 0,
+        ver: user.token_version,
+        sid: None,
+        admin: user.is_admin,
+    })
+}
+
+pub async fn get_tokens(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Vec<AccessToken>>, (StatusCode, String)> {
+    require_session(&claims)?;
+
+    let tokens = sqlx::query_as::<_, AccessToken>(
+        "SELECT id, name, scope, created_at, last_used_at
+         FROM personal_access_tokens
+         WHERE user_id = ?
+         ORDER BY created_at DESC, name",
+    )
+    .bind(claims.uid)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(tokens))
+}
+
+pub async fn create_token(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<CreateAccessToken>,
+) -> Result<Json<CreatedAccessToken>, (StatusCode, String)> {
+    require_session(&claims)?;
+
+    let name = payload.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_TOKEN_NAME_CHARS {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Token name must be between 1 and {} characters",
+                MAX_TOKEN_NAME_CHARS
+            ),
+        ));
+    }
+
+    let id = Uuid::new_v4().to_string();
+    let token = format!(
+        "{}{}",
+        ACCESS_TOKEN_PREFIX,
+        random_token(ACCESS_TOKEN_SECRET_LEN)
+    );
+    let token_hash = hash_token(&token);
+
+    let info = sqlx::query_as::<_, AccessToken>(
+        "INSERT INTO personal_access_tokens (id, user_id, name, scope, token_hash)
+         VALUES (?, ?, ?, ?, ?)
+         RETURNING id, name, scope, created_at, last_used_at",
+    )
+    .bind(&id)
+    .bind(claims.uid)
+    .bind(name)
+    .bind(payload.scope)
+    .bind(&token_hash)
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(CreatedAccessToken { info, token }))
+}
+
+pub async fn delete_token(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    require_session(&claims)?;
+
+    let result = sqlx::query!(
+        "DELETE FROM personal_access_tokens WHERE id = ? AND user_id = ?",
+        id,
+        claims.uid
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Token not found".to_string()));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,348 @@
+use axum::{Extension, Json, extract::State, http::StatusCode};
+use sqlx::{SqliteConnection, SqlitePool};
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::auth::{hash_token, random_token, verify_password};
+use crate::models::{Claims, ConfirmPassword, ConfirmTotp, RecoveryCodes, TotpEnrollment, User};
+use crate::sessions::require_session;
+
+const ISSUER: &str = "Stuff Tracker";
+const DIGITS: usize = 6;
+const STEP_SECS: u64 = 30;
+// Accept the previous and next code too, for clocks that are slightly off
+const SKEW_STEPS: u64 = 1;
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_HALF_LEN: usize = 5;
+
+fn build_totp(secret: &str, username: &str) -> Result<TOTP, (StatusCode, String)> {
+    let bytes = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    TOTP::new(
+        Algorithm::SHA1,
+        DIGITS,
+        // Skew is applied in matching_step so the matched step is known
+        0,
+        STEP_SECS,
+        bytes,
+        Some(ISSUER.to_string()),
+        username.to_string(),
+    )
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Time step `code` belongs to, if it is valid now and newer than `last_step`.
+fn matching_step(totp: &TOTP, code: &str, last_step: Option<i64>) -> Option<i64> {
+    let now = chrono::Utc::now().timestamp() as u64;
+    let current = now / STEP_SECS;
+
+    (current.saturating_sub(SKEW_STEPS)..=current + SKEW_STEPS)
+        .filter(|&step| last_step.is_none_or(|last| step as i64 > last))
+        .find(|&step| totp.check(code.trim(), step * STEP_SECS))
+        .map(|step| step as i64)
+}
+
+/// Checks a TOTP code for an enrolled user and marks its step as used.
+async fn use_totp_code(
+    conn: &mut SqliteConnection,
+    user_id: i64,
+    code: &str,
+) -> Result<bool, (StatusCode, String)> {
+    let row = sqlx::query!(
+        "SELECT username, totp_secret, totp_last_step FROM users WHERE id = ?",
+        user_id
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let Some(secret) = row.totp_secret else {
+        return Ok(false);
+    };
+
+    let totp = build_totp(&secret, &row.username)?;
+    let Some(step) = matching_step(&totp, code, row.totp_last_step) else {
+        return Ok(false);
+    };
+
+    // Conditional, so of two logins racing with the same code only one wins
+    let result = sqlx::query!(
+        "UPDATE users SET totp_last_step = ?
+         WHERE id = ? AND (totp_last_step IS NULL OR totp_last_step < ?)",
+        step,
+        user_id,
+        step
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Spends an unused recovery code.
+async fn use_recovery_code(
+    conn: &mut SqliteConnection,
+    user_id: i64,
+    code: &str,
+) -> Result<bool, (StatusCode, String)> {
+    let code_hash = hash_token(&code.trim().to_lowercase());
+    let result = sqlx::query!(
+        "UPDATE recovery_codes SET used_at = CURRENT_TIMESTAMP
+         WHERE user_id = ? AND code_hash = ? AND used_at IS NULL",
+        user_id,
+        code_hash
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Second step of logging in for users with two-factor authentication on.
+pub(crate) async fn verify_second_factor(
+    pool: &SqlitePool,
+    user: &User,
+    totp_code: Option<&str>,
+    recovery_code: Option<&str>,
+) -> Result<bool, (StatusCode, String)> {
+    if !user.totp_enabled {
+        return Ok(true);
+    }
+
+    let mut conn = pool
+        .acquire()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    match (totp_code, recovery_code) {
+        (Some(code), _) => use_totp_code(&mut conn, user.id, code).await,
+        (None, Some(code)) => use_recovery_code(&mut conn, user.id, code).await,
+        (None, None) => Ok(false),
+    }
+}
+
+/// Replaces any recovery codes with a fresh set, returned in plain text once.
+async fn issue_recovery_codes(
+    conn: &mut SqliteConnection,
+    user_id: i64,
+) -> Result<Vec<String>, (StatusCode, String)> {
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = ?", user_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let mut codes = Vec::with_capacity(RECOVERY_CODE_COUNT);
+    for _ in 0..RECOVERY_CODE_COUNT {
+        let code = format!(
+            "{}-{}",
+            random_token(RECOVERY_CODE_HALF_LEN),
+            random_token(RECOVERY_CODE_HALF_LEN)
+        )
+        .to_lowercase();
+        let code_hash = hash_token(&code);
+
+        sqlx::query!(
+            "INSERT INTO recovery_codes (user_id, code_hash) VALUES (?, ?)",
+            user_id,
+            code_hash
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        codes.push(code);
+    }
+
+    Ok(codes)
+}
+
+/// Starts enrollment with a new secret. Nothing changes at login until a code
+/// from it is confirmed.
+pub async fn enroll_totp(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<TotpEnrollment>, (StatusCode, String)> {
+    require_session(&claims)?;
+
+    let user = sqlx::query!(
+        "SELECT username, totp_enabled FROM users WHERE id = ?",
+        claims.uid
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, "Account not found".to_string()))?;
+
+    if user.totp_enabled {
+        return Err((
+            StatusCode::CONFLICT,
+            "Two-factor authentication is already enabled".to_string(),
+        ));
+    }
+
+    let secret = match Secret::generate_secret().to_encoded() {
+        Secret::Encoded(secret) => secret,
+        Secret::Raw(_) => unreachable!("to_encoded always encodes"),
+    };
+    let totp = build_totp(&secret, &user.username)?;
+
+    sqlx::query!(
+        "UPDATE users SET totp_secret = ?, totp_last_step = NULL WHERE id = ?",
+        secret,
+        claims.uid
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(TotpEnrollment {
+        secret,
+        otpauth_uri: totp.get_url(),
+    }))
+}
+
+pub async fn confirm_totp(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<ConfirmTotp>,
+) -> Result<Json<RecoveryCodes>, (StatusCode, String)> {
+    require_session(&claims)?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let user = sqlx::query!(
+        "SELECT totp_secret, totp_enabled FROM users WHERE id = ?",
+        claims.uid
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, "Account not found".to_string()))?;
+
+    if user.totp_enabled {
+        return Err((
+            StatusCode::CONFLICT,
+            "Two-factor authentication is already enabled".to_string(),
+        ));
+    }
+    if user.totp_secret.is_none() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Start enrollment before confirming a code".to_string(),
+        ));
+    }
+
+    if !use_totp_code(&mut tx, claims.uid, &payload.code).await? {
+        return Err((StatusCode::FORBIDDEN, "Invalid code".to_string()));
+    }
+
+    sqlx::query!("UPDATE users SET totp_enabled = 1 WHERE id = ?", claims.uid)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let recovery_codes = issue_recovery_codes(&mut tx, claims.uid).await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(RecoveryCodes { recovery_codes }))
+}
+
+pub async fn disable_totp(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<ConfirmPassword>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    require_session(&claims)?;
+
+    let password_hash =
+        sqlx::query_scalar!("SELECT password_hash FROM users WHERE id = ?", claims.uid)
+            .fetch_optional(&pool)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            .ok_or((StatusCode::NOT_FOUND, "Account not found".to_string()))?;
+
+    if !verify_password(&payload.current_password, &password_hash)? {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Current password is incorrect".to_string(),
+        ));
+    }
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    sqlx::query!(
+        "UPDATE users SET totp_secret = NULL, totp_enabled = 0, totp_last_step = NULL
+         WHERE id = ?",
+        claims.uid
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = ?", claims.uid)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Replaces the recovery codes, e.g. once most of them have been used.
+pub async fn regenerate_recovery_codes(
+    State(pool): State<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<ConfirmPassword>,
+) -> Result<Json<RecoveryCodes>, (StatusCode, String)> {
+    require_session(&claims)?;
+
+    let user = sqlx::query!(
+        "SELECT password_hash, totp_enabled FROM users WHERE id = ?",
+        claims.uid
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, "Account not found".to_string()))?;
+
+    if !verify_password(&payload.current_password, &user.password_hash)? {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Current password is incorrect".to_string(),
+        ));
+    }
+    if !user.totp_enabled {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Two-factor authentication is not enabled".to_string(),
+        ));
+    }
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let recovery_codes = issue_recovery_codes(&mut tx, claims.uid).await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(RecoveryCodes { recovery_codes }))
+}
@@ -1,52 +1,255 @@
-use axum::{Json, extract::Multipart, http::StatusCode};
+use axum::{
+    Extension, Json,
+    body::Body,
+    extract::{Multipart, Request, State},
+    http::{HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use image::{
+    ColorType, DynamicImage, ImageDecoder, ImageError, ImageFormat, ImageReader, ImageResult,
+    Limits,
+    codecs::{avif::AvifEncoder, jpeg::JpegEncoder, webp::WebPEncoder},
+    error::LimitErrorKind,
+    imageops::FilterType,
+    metadata::Orientation,
+};
 use serde::Serialize;
-use std::path::Path;
+use sha2::{Digest, Sha256};
+use sqlx::{SqliteConnection, SqlitePool};
+use std::borrow::Cow;
+use std::io::{BufWriter, Cursor};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio::fs;
+use tower_http::services::ServeFile;
 use uuid::Uuid;
 
+use crate::models::{Claims, ImageVariants};
+
+// Resized copies saved next to every upload, as the file name suffix and the
+// longest side in pixels. The original keeps its size.
+const VARIANTS: [(&str, u32); 2] = [("thumb", 160), ("card", 640)];
+
+// What browsers and phones produce; anything else is refused rather than
+// handed to a rarely used decoder
+const ACCEPTED_FORMATS: [ImageFormat; 4] = [
+    ImageFormat::Jpeg,
+    ImageFormat::Png,
+    ImageFormat::Gif,
+    ImageFormat::WebP,
+];
+
+// Memory a decoded image and its working copies may take. Enough for the
+// largest allowed image as 8-bit RGBA plus one full size copy; a small file
+// claiming more is a decompression bomb.
+const MAX_DECODED_BYTES: u64 = 512 * 1024 * 1024;
+
+// Room for the multipart boundaries and headers around the image
+const MULTIPART_OVERHEAD: usize = 64 * 1024;
+
+/// Formats an upload can additionally be saved in, next to the JPEG (or PNG
+/// for transparent images) that every browser can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtraFormat {
+    Avif,
+    // Lossless only, as the image crate has no lossy encoder, so it only wins
+    // for transparent images and flat graphics
+    WebP,
+}
+
+impl ExtraFormat {
+    const ALL: [ExtraFormat; 2] = [ExtraFormat::Avif, ExtraFormat::WebP];
+
+    fn extension(self) -> &'static str {
+        match self {
+            ExtraFormat::Avif => "avif",
+            ExtraFormat::WebP => "webp",
+        }
+    }
+
+    fn mime(self) -> &'static str {
+        match self {
+            ExtraFormat::Avif => "image/avif",
+            ExtraFormat::WebP => "image/webp",
+        }
+    }
+}
+
+/// How uploads are checked and encoded.
+#[derive(Debug, Clone)]
+pub struct UploadSettings {
+    // Largest file accepted
+    pub max_bytes: usize,
+    // Longest side in pixels
+    pub max_dimension: u32,
+    pub formats: Vec<ExtraFormat>,
+    // 1-100
+    pub jpeg_quality: u8,
+    // 1-100
+    pub avif_quality: u8,
+    // 1 (slowest, smallest) to 10
+    pub avif_speed: u8,
+}
+
+impl Default for UploadSettings {
+    fn default() -> Self {
+        Self {
+            max_bytes: 10 * 1024 * 1024,
+            max_dimension: 8192,
+            formats: vec![ExtraFormat::Avif, ExtraFormat::WebP],
+            jpeg_quality: 85,
+            avif_quality: 70,
+            avif_speed: 8,
+        }
+    }
+}
+
+impl UploadSettings {
+    /// Reads `UPLOAD_MAX_MB`, `UPLOAD_MAX_DIMENSION`, `UPLOAD_FORMATS` (e.g.
+    /// `avif,webp`, or empty for none), `JPEG_QUALITY`, `AVIF_QUALITY` and
+    /// `AVIF_SPEED`. Unset ones keep their defaults.
+    pub fn from_env() -> Result<Self, String> {
+        let mut settings = Self::default();
+
+        let max_mb = env_number("UPLOAD_MAX_MB", settings.max_bytes / (1024 * 1024), 1..=100)?;
+        settings.max_bytes = max_mb * 1024 * 1024;
+        settings.max_dimension =
+            env_number("UPLOAD_MAX_DIMENSION", settings.max_dimension, 160..=8192)?;
+
+        if let Ok(list) = std::env::var("UPLOAD_FORMATS") {
+            settings.formats = list
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(|name| {
+                    ExtraFormat::ALL
+                        .into_iter()
+                        .find(|format| format.extension().eq_ignore_ascii_case(name))
+                        .ok_or_else(|| format!("Unknown UPLOAD_FORMATS entry '{}'", name))
+                })
+                .collect::<Result<_, _>>()?;
+        }
+        settings.jpeg_quality = env_number("JPEG_QUALITY", settings.jpeg_quality, 1..=100)?;
+        settings.avif_quality = env_number("AVIF_QUALITY", settings.avif_quality, 1..=100)?;
+        settings.avif_speed = env_number("AVIF_SPEED", settings.avif_speed, 1..=10)?;
+
+        Ok(settings)
+    }
+
+    /// The request body limit for the upload route.
+    pub fn body_limit(&self) -> usize {
+        self.max_bytes + MULTIPART_OVERHEAD
+    }
+
+    fn decode_limits(&self) -> Limits {
+        let mut limits = Limits::default();
+        limits.max_image_width = Some(self.max_dimension);
+        limits.max_image_height = Some(self.max_dimension);
+        limits.max_alloc = Some(MAX_DECODED_BYTES);
+        limits
+    }
+}
+
+fn env_number<T>(name: &str, default: T, range: std::ops::RangeInclusive<T>) -> Result<T, String>
+where
+    T: std::str::FromStr + PartialOrd + std::fmt::Display,
+{
+    let Ok(value) = std::env::var(name) else {
+        return Ok(default);
+    };
+    value
+        .trim()
+        .parse()
+        .ok()
+        .filter(|n| range.contains(n))
+        .ok_or_else(|| {
+            format!(
+                "{} must be a number from {} to {}",
+                name,
+                range.start(),
+                range.end()
+            )
+        })
+}
+
 #[derive(Serialize)]
 pub struct UploadResponse {
+    // The full size image, which is what items and categories store
     url: String,
+    variants: ImageVariants,
+}
+
+/// What is recorded in the uploads table about a saved upload.
+struct SavedUpload {
+    url: String,
+    // Of every file written, the resized copies and other formats included
+    size: i64,
+    width: u32,
+    height: u32,
+    hash: String,
 }
 
 pub async fn upload_image(
+    State(pool): State<SqlitePool>,
+    State(settings): State<Arc<UploadSettings>>,
+    Extension(claims): Extension<Claims>,
     mut multipart: Multipart,
 ) -> Result<Json<UploadResponse>, (StatusCode, String)> {
-    while let Some(field) = multipart
+    while let Some(mut field) = multipart
         .next_field()
         .await
-        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?
+        .map_err(|e| (e.status(), e.body_text()))?
     {
         let name = field.name().unwrap_or("").to_string();
 
         if name == "image" {
-            // Just force .jpg extension since we are converting everything to jpeg effectively
-            let new_filename = format!("{}.jpg", Uuid::new_v4());
-            let filepath = format!("uploads/{}", new_filename);
+            // Read in pieces so an oversized file is turned away without
+            // holding all of it
+            let mut data = Vec::new();
+            while let Some(chunk) = field
+                .chunk()
+                .await
+                .map_err(|e| (e.status(), e.body_text()))?
+            {
+                if data.len() + chunk.len() > settings.max_bytes {
+                    return Err((
+                        StatusCode::PAYLOAD_TOO_LARGE,
+                        format!(
+                            "Images can be at most {} MB",
+                            settings.max_bytes / (1024 * 1024)
+                        ),
+                    ));
+                }
+                data.extend_from_slice(&chunk);
+            }
 
-            let data = field
-                .bytes()
+            // Encoding, AVIF especially, would hold up other requests
+            let saved = tokio::task::spawn_blocking(move || save_upload(&data, &settings))
                 .await
-                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-            // Strip metadata by decoding and re-encoding
-            let img = image::load_from_memory(&data).map_err(|e| {
-                (
-                    StatusCode::BAD_REQUEST,
-                    format!("Invalid image format: {}", e),
-                )
-            })?;
-
-            // Save as JPEG with default quality
-            img.save(&filepath).map_err(|e| {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Failed to save image: {}", e),
-                )
-            })?;
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))??;
+
+            let recorded = sqlx::query!(
+                "INSERT INTO uploads (url, user_id, size, width, height, hash)
+                 VALUES (?, ?, ?, ?, ?, ?)",
+                saved.url,
+                claims.uid,
+                saved.size,
+                saved.width,
+                saved.height,
+                saved.hash
+            )
+            .execute(&pool)
+            .await;
+            if let Err(e) = recorded {
+                // Nobody could use it anyway
+                let _ = delete_image(&saved.url).await;
+                return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+            }
 
+            let url = saved.url;
             return Ok(Json(UploadResponse {
-                url: format!("/uploads/{}", new_filename),
+                variants: image_variants(&url).expect("upload URLs always have variants"),
+                url,
             }));
         }
     }
@@ -54,11 +257,327 @@ pub async fn upload_image(
     Err((StatusCode::BAD_REQUEST, "No image field found".to_string()))
 }
 
-pub async fn delete_image(url: &str) -> std::io::Result<()> {
+/// Decodes an upload and writes it with its variants.
+fn save_upload(
+    data: &[u8],
+    settings: &UploadSettings,
+) -> Result<SavedUpload, (StatusCode, String)> {
+    let format = image::guess_format(data)
+        .ok()
+        .filter(|format| ACCEPTED_FORMATS.contains(format))
+        .ok_or((
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "Images must be JPEG, PNG, GIF or WebP".to_string(),
+        ))?;
+
+    // Strip metadata by decoding and re-encoding
+    let img = decode_upright(data, format, settings.decode_limits()).map_err(|e| match e {
+        ImageError::Limits(ref limit) if limit.kind() == LimitErrorKind::DimensionError => (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!(
+                "Images can be at most {0}x{0} pixels",
+                settings.max_dimension
+            ),
+        ),
+        ImageError::Limits(_) => (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "Image is too large to process".to_string(),
+        ),
+        ImageError::Unsupported(_) => (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            format!("Unsupported image: {}", e),
+        ),
+        _ => (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid image format: {}", e),
+        ),
+    })?;
+
+    // JPEG has no alpha channel, so transparent images stay PNG
+    let extension = if has_transparency(&img) { "png" } else { "jpg" };
+    let url = format!("/uploads/{}.{}", Uuid::new_v4(), extension);
+
+    let save_failed = |e: &dyn std::fmt::Display| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to save image: {}", e),
+        )
+    };
+    save_image(&img, &url, settings)
+        .and_then(|()| save_variants(&img, &url, settings))
+        .map_err(|e| save_failed(&e))?;
+    // Described as written, after re-encoding
+    let saved = local_path(&url)
+        .and_then(|filepath| std::fs::read(filepath.expect("upload URLs are local")))
+        .map_err(|e| save_failed(&e))?;
+
+    let size = image_size(&url).map_err(|e| save_failed(&e))?;
+
+    Ok(SavedUpload {
+        size: size as i64,
+        width: img.width(),
+        height: img.height(),
+        hash: hex::encode(Sha256::digest(&saved)),
+        url,
+    })
+}
+
+/// Decodes an uploaded image with its pixels turned the way its EXIF
+/// orientation says. Re-encoding drops the tag along with the rest of the
+/// metadata, so phone photos would otherwise come out sideways.
+///
+/// `limits` is checked against the header before any pixels are decoded,
+/// counting the full size copies made on the way to being saved.
+fn decode_upright(
+    data: &[u8],
+    format: ImageFormat,
+    mut limits: Limits,
+) -> ImageResult<DynamicImage> {
+    let mut reader = ImageReader::with_format(Cursor::new(data), format);
+    reader.limits(limits.clone());
+    let mut decoder = reader.into_decoder()?;
+
+    // Unreadable EXIF isn't worth rejecting the photo over
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+
+    // Rotating by a quarter turn builds a new image, dropping the old one
+    // only once done
+    let rotation_bytes = match orientation {
+        Orientation::Rotate90
+        | Orientation::Rotate270
+        | Orientation::Rotate90FlipH
+        | Orientation::Rotate270FlipH => decoder.total_bytes(),
+        _ => 0,
+    };
+    // save_image converts anything but 8-bit RGB(A), after any rotation. An
+    // opaque RGBA image is saved as a JPEG, so it is converted to RGB.
+    let (width, height) = decoder.dimensions();
+    let conversion_bytes = match decoder.color_type() {
+        ColorType::Rgb8 => 0,
+        ColorType::Rgba8 => u64::from(width) * u64::from(height) * 3,
+        color => u64::from(width) * u64::from(height) * if color.has_alpha() { 4 } else { 3 },
+    };
+    // Not every decoder enforces max_alloc itself
+    limits.reserve(decoder.total_bytes())?;
+    limits.reserve(rotation_bytes.max(conversion_bytes))?;
+
+    let mut img = DynamicImage::from_decoder(decoder)?;
+    img.apply_orientation(orientation);
+    Ok(img)
+}
+
+/// Whether any pixel is less than fully opaque, checked without a copy for
+/// the color types the decoders produce.
+fn has_transparency(img: &DynamicImage) -> bool {
+    match img {
+        DynamicImage::ImageLumaA8(buffer) => buffer.pixels().any(|pixel| pixel[1] < u8::MAX),
+        DynamicImage::ImageRgba8(buffer) => buffer.pixels().any(|pixel| pixel[3] < u8::MAX),
+        DynamicImage::ImageLumaA16(buffer) => buffer.pixels().any(|pixel| pixel[1] < u16::MAX),
+        DynamicImage::ImageRgba16(buffer) => buffer.pixels().any(|pixel| pixel[3] < u16::MAX),
+        DynamicImage::ImageRgba32F(buffer) => buffer.pixels().any(|pixel| pixel[3] < 1.0),
+        _ => img.color().has_alpha(),
+    }
+}
+
+/// Writes `img` to the upload at `url` as a JPEG or PNG, going by the URL's
+/// extension, plus a copy in each extra format that turns out smaller.
+fn save_image(img: &DynamicImage, url: &str, settings: &UploadSettings) -> ImageResult<()> {
+    let Ok(Some(filepath)) = local_path(url) else {
+        return Ok(());
+    };
+
+    // Only converted when not already what the encoders are given
+    let transparent = url.ends_with(".png");
+    let pixels = match img {
+        DynamicImage::ImageRgba8(_) if transparent => Cow::Borrowed(img),
+        DynamicImage::ImageRgb8(_) if !transparent => Cow::Borrowed(img),
+        _ if transparent => Cow::Owned(DynamicImage::ImageRgba8(img.to_rgba8())),
+        _ => Cow::Owned(DynamicImage::ImageRgb8(img.to_rgb8())),
+    };
+
+    if transparent {
+        pixels.save_with_format(&filepath, ImageFormat::Png)?;
+    } else {
+        let file = BufWriter::new(std::fs::File::create(&filepath)?);
+        pixels.write_with_encoder(JpegEncoder::new_with_quality(file, settings.jpeg_quality))?;
+    }
+    let fallback_size = std::fs::metadata(&filepath)?.len();
+
+    for format in ExtraFormat::ALL {
+        let Some(alternative) =
+            alternative_url(url, format).and_then(|a| local_path(&a).ok().flatten())
+        else {
+            continue;
+        };
+
+        let mut encoded = Vec::new();
+        if settings.formats.contains(&format) {
+            match format {
+                ExtraFormat::Avif => {
+                    pixels.write_with_encoder(AvifEncoder::new_with_speed_quality(
+                        &mut encoded,
+                        settings.avif_speed,
+                        settings.avif_quality,
+                    ))?
+                }
+                ExtraFormat::WebP => {
+                    pixels.write_with_encoder(WebPEncoder::new_lossless(&mut encoded))?
+                }
+            }
+        }
+
+        // Not worth serving if it isn't smaller; also clears out a stale copy
+        // when regenerating with a format turned off
+        if !encoded.is_empty() && (encoded.len() as u64) < fallback_size {
+            std::fs::write(alternative, encoded)?;
+        } else if alternative.exists() {
+            std::fs::remove_file(alternative)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// URL of one of an upload's resized copies: `/uploads/ID.jpg` becomes
+/// `/uploads/ID-thumb.jpg`.
+fn variant_url(url: &str, suffix: &str) -> Option<String> {
+    let filename = url.strip_prefix("/uploads/")?;
+    let (stem, ext) = filename.rsplit_once('.')?;
+    Some(format!("/uploads/{}-{}.{}", stem, suffix, ext))
+}
+
+/// URL of the same image in another format: `/uploads/ID.jpg` becomes
+/// `/uploads/ID.avif`.
+fn alternative_url(url: &str, format: ExtraFormat) -> Option<String> {
+    let filename = url.strip_prefix("/uploads/")?;
+    let (stem, _) = filename.rsplit_once('.')?;
+    Some(format!("/uploads/{}.{}", stem, format.extension()))
+}
+
+/// Every size of a local upload, or None for images hosted elsewhere.
+pub fn image_variants(url: &str) -> Option<ImageVariants> {
+    let [(thumbnail, _), (card, _)] = VARIANTS;
+    Some(ImageVariants {
+        thumbnail: variant_url(url, thumbnail)?,
+        card: variant_url(url, card)?,
+        full: url.to_string(),
+    })
+}
+
+/// What all the files of one upload have in common: `/uploads/ID-thumb.avif`
+/// and `/uploads/ID.jpg` both give `/uploads/ID`.
+pub fn upload_key(url: &str) -> &str {
+    let stem = url.rsplit_once('.').map_or(url, |(stem, _)| stem);
+    VARIANTS
+        .iter()
+        .find_map(|(suffix, _)| stem.strip_suffix(suffix)?.strip_suffix('-'))
+        .unwrap_or(stem)
+}
+
+/// Whether a file is the JPEG or PNG an upload's URL points at, rather than
+/// a resized copy or another format.
+pub fn is_original(url: &str) -> bool {
+    (url.ends_with(".jpg") || url.ends_with(".png")) && upload_key(url).len() + 4 == url.len()
+}
+
+/// Writes the resized copies of the upload at `url`. Images already smaller
+/// than a variant are copied at their own size rather than enlarged.
+pub fn save_variants(img: &DynamicImage, url: &str, settings: &UploadSettings) -> ImageResult<()> {
+    for (suffix, size) in VARIANTS {
+        let Some(variant) = variant_url(url, suffix) else {
+            continue;
+        };
+
+        if img.width() <= size && img.height() <= size {
+            save_image(img, &variant, settings)?;
+        } else {
+            save_image(
+                &img.resize(size, size, FilterType::Lanczos3),
+                &variant,
+                settings,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the resized copies and extra formats of an upload already on disk.
+pub fn regenerate_variants(url: &str, settings: &UploadSettings) -> ImageResult<()> {
+    let Ok(Some(filepath)) = local_path(url) else {
+        return Ok(());
+    };
+
+    let img = image::open(filepath)?;
+    save_image(&img, url, settings)?;
+    save_variants(&img, url, settings)
+}
+
+/// Serves an upload, swapping in a smaller AVIF or WebP copy of it when the
+/// request's `Accept` header allows.
+pub async fn serve_upload(
+    axum::extract::Path(filename): axum::extract::Path<String>,
+    request: Request,
+) -> Response {
+    let url = format!("/uploads/{}", filename);
+    let Ok(Some(mut filepath)) = local_path(&url) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let accept = request
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+
+    if let Ok(metadata) = fs::metadata(&filepath).await {
+        let mut smallest = metadata.len();
+        for format in ExtraFormat::ALL {
+            if !accepts(accept, format.mime()) {
+                continue;
+            }
+            let Some(alternative) =
+                alternative_url(&url, format).and_then(|a| local_path(&a).ok().flatten())
+            else {
+                continue;
+            };
+            if let Ok(metadata) = fs::metadata(&alternative).await
+                && metadata.len() < smallest
+            {
+                smallest = metadata.len();
+                filepath = alternative;
+            }
+        }
+    }
+
+    let mut response = match ServeFile::new(filepath).try_call(request).await {
+        Ok(response) => response.map(Body::new),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+    // Caches have to keep a copy per format
+    response
+        .headers_mut()
+        .insert(header::VARY, HeaderValue::from_static("accept"));
+    response
+}
+
+/// Whether an `Accept` header lists `mime` explicitly. Wildcards don't count:
+/// browsers send `*/*` for formats they can't show too.
+fn accepts(accept: &str, mime: &str) -> bool {
+    accept.split(',').any(|entry| {
+        let mut parts = entry.split(';').map(str::trim);
+        parts.next() == Some(mime)
+            && !parts.any(|param| {
+                param.strip_prefix("q=").and_then(|q| q.parse::<f32>().ok()) == Some(0.0)
+            })
+    })
+}
+
+/// Path on disk of an uploaded image, or None for URLs that aren't local uploads.
+fn local_path(url: &str) -> std::io::Result<Option<PathBuf>> {
     // URL format: /uploads/UUID.ext
     let filename = match url.strip_prefix("/uploads/") {
         Some(f) => f,
-        None => return Ok(()), // Not a local upload path, ignore
+        None => return Ok(None), // Not a local upload path, ignore
     };
 
     // Basic sanity check on filename to prevent obvious directory traversal
@@ -69,12 +588,133 @@ pub async fn delete_image(url: &str) -> std::io::Result<()> {
         ));
     }
 
-    let filepath = Path::new("uploads").join(filename);
+    Ok(Some(Path::new("uploads").join(filename)))
+}
+
+/// Every file on disk that makes up an upload: the original and its
+/// variants, each with its copies in other formats.
+fn upload_files(url: &str) -> std::io::Result<Vec<PathBuf>> {
+    if local_path(url)?.is_none() {
+        return Ok(Vec::new());
+    }
+
+    let mut urls = vec![url.to_string()];
+    urls.extend(
+        VARIANTS
+            .iter()
+            .filter_map(|(suffix, _)| variant_url(url, suffix)),
+    );
+
+    let mut files = Vec::new();
+    for url in urls {
+        for format in ExtraFormat::ALL {
+            if let Some(alternative) = alternative_url(&url, format) {
+                files.extend(local_path(&alternative)?);
+            }
+        }
+        files.extend(local_path(&url)?);
+    }
+    Ok(files)
+}
+
+/// Checks an image URL someone wants to put on an item or category. Links to
+/// other sites are fine; local ones have to be the user's own uploads.
+pub(crate) async fn require_own_upload(
+    pool: &SqlitePool,
+    url: &str,
+    user_id: i64,
+) -> Result<(), (StatusCode, String)> {
+    if !url.starts_with("/uploads/") {
+        return Ok(());
+    }
+
+    let owned = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM uploads WHERE url = ? AND user_id = ?)",
+    )
+    .bind(url)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if !owned {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "You can only use images you uploaded".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Forgets the uploads among `urls` that no item or category uses any more
+/// and returns them, for their files to be deleted once committed. Paths that
+/// were never uploaded are left alone.
+pub(crate) async fn release_uploads(
+    conn: &mut SqliteConnection,
+    urls: Vec<String>,
+) -> Result<Vec<String>, sqlx::Error> {
+    let mut released = Vec::new();
+    for url in urls {
+        let result = sqlx::query(
+            "DELETE FROM uploads WHERE url = ?
+               AND NOT EXISTS(SELECT 1 FROM items WHERE image_url = uploads.url)
+               AND NOT EXISTS(SELECT 1 FROM categories WHERE cover_image_url = uploads.url)",
+        )
+        .bind(&url)
+        .execute(&mut *conn)
+        .await?;
+        if result.rows_affected() > 0 {
+            released.push(url);
+        }
+    }
+    Ok(released)
+}
 
-    // Verify existence to avoid errors on already deleted files
-    if filepath.exists() {
-        fs::remove_file(filepath).await?;
+/// Deletes an upload along with its resized copies and other formats.
+pub async fn delete_image(url: &str) -> std::io::Result<()> {
+    for filepath in upload_files(url)? {
+        // Verify existence to avoid errors on already deleted files
+        if filepath.exists() {
+            fs::remove_file(filepath).await?;
+        }
     }
 
     Ok(())
 }
+
+/// Bytes an upload takes up on disk, counting all its copies.
+pub fn image_size(url: &str) -> std::io::Result<u64> {
+    let mut size = 0;
+    for filepath in upload_files(url)? {
+        // Copies that didn't turn out smaller aren't kept
+        match std::fs::metadata(filepath) {
+            Ok(metadata) => size += metadata.len(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(size)
+}
+
+/// Every file in the uploads directory, as its URL and metadata.
+pub async fn uploaded_files() -> std::io::Result<Vec<(String, std::fs::Metadata)>> {
+    let mut files = Vec::new();
+    let mut entries = match fs::read_dir("uploads").await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(files),
+        Err(e) => return Err(e),
+    };
+    while let Some(entry) = entries.next_entry().await? {
+        let metadata = entry.metadata().await?;
+        if metadata.is_file() {
+            let url = format!("/uploads/{}", entry.file_name().to_string_lossy());
+            files.push((url, metadata));
+        }
+    }
+    Ok(files)
+}
+
+/// Total size of everything in the uploads directory.
+pub async fn uploads_size() -> std::io::Result<u64> {
+    Ok(uploaded_files().await?.iter().map(|(_, m)| m.len()).sum())
+}
@@ -0,0 +1,332 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use serde_json::json;
+use server::{auth::JwtKeys, create_router, models::Account, state::AppState};
+use sqlx::SqlitePool;
+use tower::ServiceExt; // for `oneshot`
+
+#[sqlx::test]
+async fn test_change_password(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    create_user(&pool, "alice", "old password").await;
+    let old_token = login(&app, "alice", "old password").await.unwrap();
+    let other_device = login(&app, "alice", "old password").await.unwrap();
+
+    // 1. The current password is required
+    let (status, _) = send(
+        &app,
+        &old_token,
+        "POST",
+        "/api/account/password",
+        Some(json!({ "current_password": "wrong", "new_password": "new password" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // 2. The new password must be long enough
+    let (status, _) = send(
+        &app,
+        &old_token,
+        "POST",
+        "/api/account/password",
+        Some(json!({ "current_password": "old password", "new_password": "short" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // 3. Changing it hands back a fresh token
+    let (status, body) = send(
+        &app,
+        &old_token,
+        "POST",
+        "/api/account/password",
+        Some(json!({ "current_password": "old password", "new_password": "new password" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let new_token = body["token"].as_str().unwrap().to_string();
+
+    // 4. Every previously issued token stops working
+    let (status, _) = send(&app, &old_token, "GET", "/api/items", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = send(&app, &other_device, "GET", "/api/items", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = send(&app, &new_token, "GET", "/api/items", None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // 5. Only the new password logs in
+    assert!(login(&app, "alice", "old password").await.is_none());
+    assert!(login(&app, "alice", "new password").await.is_some());
+}
+
+#[sqlx::test]
+async fn test_update_account(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    let alice_id = create_user(&pool, "alice", "password1").await;
+    create_user(&pool, "bob", "password2").await;
+    let token = login(&app, "alice", "password1").await.unwrap();
+
+    let (status, body) = send(&app, &token, "GET", "/api/account", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let account: Account = serde_json::from_value(body).unwrap();
+    assert_eq!(account.id, alice_id);
+    assert_eq!(account.username, "alice");
+
+    // 1. Taken (in any case) and invalid names are refused
+    let (status, _) = send(
+        &app,
+        &token,
+        "PATCH",
+        "/api/account",
+        Some(json!({ "username": "BOB" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = send(
+        &app,
+        &token,
+        "PATCH",
+        "/api/account",
+        Some(json!({ "username": "a" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // 2. Changing the case of your own name is fine, and the session survives a rename
+    let (status, body) = send(
+        &app,
+        &token,
+        "PATCH",
+        "/api/account",
+        Some(json!({ "username": "Alice" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let account: Account = serde_json::from_value(body).unwrap();
+    assert_eq!(account.username, "Alice");
+
+    let (status, _) = send(&app, &token, "GET", "/api/items", None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // 3. Logging in uses the new name
+    assert!(login(&app, "alice", "password1").await.is_none());
+    assert!(login(&app, "Alice", "password1").await.is_some());
+}
+
+#[sqlx::test]
+async fn test_delete_account(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    let alice_id = create_user(&pool, "alice", "password1").await;
+    let bob_id = create_user(&pool, "bob", "password2").await;
+    let token = login(&app, "alice", "password1").await.unwrap();
+    let bob_token = login(&app, "bob", "password2").await.unwrap();
+
+    // Alice has a category, items with a comparison, an image and an access token
+    let image = format!("account-test-{}.jpg", alice_id);
+    std::fs::create_dir_all("uploads").unwrap();
+    std::fs::write(format!("uploads/{}", image), b"jpeg").unwrap();
+    let image_url = format!("/uploads/{}", image);
+    sqlx::query!(
+        "INSERT INTO uploads (url, user_id) VALUES (?, ?)",
+        image_url,
+        alice_id
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    let (_, first) = send(
+        &app,
+        &token,
+        "POST",
+        "/api/items",
+        Some(json!({ "category": "Books", "name": "Dune", "image_url": image_url })),
+    )
+    .await;
+    let (_, second) = send(
+        &app,
+        &token,
+        "POST",
+        "/api/items",
+        Some(json!({ "category": "Books", "name": "Emma" })),
+    )
+    .await;
+    let first_id = first["id"].as_str().unwrap();
+    let second_id = second["id"].as_str().unwrap();
+    sqlx::query!(
+        "INSERT INTO comparisons (id, user_id, category_id, winner_id, loser_id)
+         SELECT 'cmp-1', ?, category_id, ?, ? FROM items WHERE id = ?",
+        alice_id,
+        first_id,
+        second_id,
+        first_id
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    send(
+        &app,
+        &token,
+        "POST",
+        "/api/tokens",
+        Some(json!({ "name": "script" })),
+    )
+    .await;
+    send(
+        &app,
+        &bob_token,
+        "POST",
+        "/api/items",
+        Some(json!({ "category": "Books", "name": "Ulysses" })),
+    )
+    .await;
+
+    // 1. The password is required
+    let (status, _) = send(
+        &app,
+        &token,
+        "DELETE",
+        "/api/account",
+        Some(json!({ "current_password": "wrong" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // 2. Deleting removes every row and the image
+    let (status, _) = send(
+        &app,
+        &token,
+        "DELETE",
+        "/api/account",
+        Some(json!({ "current_password": "password1" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert!(!std::path::Path::new(&format!("uploads/{}", image)).exists());
+
+    for table in [
+        "items",
+        "categories",
+        "comparisons",
+        "sessions",
+        "personal_access_tokens",
+        "uploads",
+    ] {
+        let count: i64 =
+            sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {} WHERE user_id = ?", table))
+                .bind(alice_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(count, 0, "{} left behind", table);
+    }
+
+    // 3. The account is gone; bob's things are not
+    let (status, _) = send(&app, &token, "GET", "/api/items", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(login(&app, "alice", "password1").await.is_none());
+
+    let (status, body) = send(&app, &bob_token, "GET", "/api/items", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body.as_array().unwrap().len(), 1);
+
+    // 4. The last admin has to hand over first
+    sqlx::query!("UPDATE users SET is_admin = 1 WHERE id = ?", bob_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let (status, _) = send(
+        &app,
+        &bob_token,
+        "DELETE",
+        "/api/account",
+        Some(json!({ "current_password": "password2" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+// Helpers (Duplicated for isolation as requested)
+async fn create_user(pool: &SqlitePool, username: &str, password: &str) -> i64 {
+    use argon2::{
+        Argon2,
+        password_hash::{PasswordHasher, SaltString, rand_core::OsRng},
+    };
+
+    let salt = SaltString::generate(&mut OsRng);
+    let argon2 = Argon2::default();
+    let password_hash = argon2
+        .hash_password(password.as_bytes(), &salt)
+        .unwrap()
+        .to_string();
+
+    let rec = sqlx::query!(
+        "INSERT INTO users (username, password_hash) VALUES (?, ?) RETURNING id",
+        username,
+        password_hash
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap();
+
+    rec.id
+}
+
+async fn login(app: &axum::Router, username: &str, password: &str) -> Option<String> {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/login")
+                .method("POST")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "username": username,
+                        "password": password
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    if response.status() != StatusCode::OK {
+        return None;
+    }
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    Some(json["token"].as_str().unwrap().to_string())
+}
+
+async fn send(
+    app: &axum::Router,
+    token: &str,
+    method: &str,
+    uri: &str,
+    body: Option<serde_json::Value>,
+) -> (StatusCode, serde_json::Value) {
+    let builder = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("Authorization", format!("Bearer {}", token));
+    let request = match body {
+        Some(body) => builder
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap(),
+        None => builder.body(Body::empty()).unwrap(),
+    };
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json = serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null);
+    (status, json)
+}
@@ -0,0 +1,342 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use image::{ImageFormat, RgbImage};
+use serde_json::json;
+use server::{
+    auth::JwtKeys,
+    create_router, maintenance,
+    models::{AdminUser, StorageUsage},
+    state::AppState,
+    upload::{delete_image, upload_key, uploaded_files},
+};
+use sqlx::SqlitePool;
+use std::io::Cursor;
+use tower::ServiceExt; // for `oneshot`
+
+#[sqlx::test]
+async fn test_admin_routes_require_admin(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    let alice_id = create_user(&pool, "alice", "password1").await;
+    let token = login(&app, "alice", "password1").await.unwrap();
+
+    // 1. Ordinary users are turned away
+    let (status, _) = send(&app, &token, "GET", "/api/admin/users", None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // 2. Promotion applies to tokens that are already out
+    sqlx::query!("UPDATE users SET is_admin = 1 WHERE id = ?", alice_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let (status, body) = send(&app, &token, "GET", "/api/admin/users", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let users: Vec<AdminUser> = serde_json::from_value(body).unwrap();
+    assert_eq!(users.len(), 1);
+    assert!(users[0].is_admin);
+
+    let (status, body) = send(&app, &token, "GET", "/api/account", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["is_admin"], true);
+
+    // 3. Personal access tokens never get admin rights
+    let (_, body) = send(
+        &app,
+        &token,
+        "POST",
+        "/api/tokens",
+        Some(json!({ "name": "script", "scope": "read_write" })),
+    )
+    .await;
+    let access_token = body["token"].as_str().unwrap().to_string();
+    let (status, _) = send(&app, &access_token, "GET", "/api/admin/users", None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[sqlx::test]
+async fn test_admin_manages_users(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    let admin_id = create_user(&pool, "admin", "password1").await;
+    sqlx::query!("UPDATE users SET is_admin = 1 WHERE id = ?", admin_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let admin = login(&app, "admin", "password1").await.unwrap();
+
+    // 1. Create an account; names are still checked
+    let (status, _) = send(
+        &app,
+        &admin,
+        "POST",
+        "/api/admin/users",
+        Some(json!({ "username": "ADMIN", "password": "password2" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, body) = send(
+        &app,
+        &admin,
+        "POST",
+        "/api/admin/users",
+        Some(json!({ "username": "bob", "password": "password2" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let bob: AdminUser = serde_json::from_value(body).unwrap();
+    assert!(!bob.is_admin);
+    assert!(bob.disabled_at.is_none());
+    let bob_token = login(&app, "bob", "password2").await.unwrap();
+
+    // 2. Disabling signs bob out and blocks logins
+    let uri = format!("/api/admin/users/{}", bob.id);
+    let (status, body) = send(
+        &app,
+        &admin,
+        "PATCH",
+        &uri,
+        Some(json!({ "disabled": true })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!body["disabled_at"].is_null());
+    let (status, _) = send(&app, &bob_token, "GET", "/api/items", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(login(&app, "bob", "password2").await.is_none());
+
+    let (status, _) = send(
+        &app,
+        &admin,
+        "PATCH",
+        &uri,
+        Some(json!({ "disabled": false })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let bob_token = login(&app, "bob", "password2").await.unwrap();
+
+    // 3. Resetting the password signs bob out too
+    let (status, _) = send(
+        &app,
+        &admin,
+        "POST",
+        &format!("{}/password", uri),
+        Some(json!({ "new_password": "password3" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send(&app, &bob_token, "GET", "/api/items", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(login(&app, "bob", "password2").await.is_none());
+    assert!(login(&app, "bob", "password3").await.is_some());
+
+    // 4. Admins can't lock themselves out
+    let self_uri = format!("/api/admin/users/{}", admin_id);
+    let (status, _) = send(
+        &app,
+        &admin,
+        "PATCH",
+        &self_uri,
+        Some(json!({ "is_admin": false })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send(
+        &app,
+        &admin,
+        "PATCH",
+        &self_uri,
+        Some(json!({ "disabled": true })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = send(
+        &app,
+        &admin,
+        "PATCH",
+        "/api/admin/users/9999",
+        Some(json!({ "is_admin": true })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // 5. Invites are handed out by admins
+    let (status, body) = send(&app, &admin, "POST", "/api/admin/invites", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let code = body["code"].as_str().unwrap().to_string();
+    let (_, body) = send(&app, &admin, "GET", "/api/admin/invites", None).await;
+    assert_eq!(body[0]["code"], code);
+
+    // 6. Storage usage lists every account, counting all of an upload's files
+    let (status, body) = send(&app, &admin, "GET", "/api/admin/storage", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let storage: StorageUsage = serde_json::from_value(body).unwrap();
+    assert_eq!(storage.users.len(), 2);
+    assert!(storage.users.iter().all(|u| u.image_count == 0));
+
+    std::fs::create_dir_all("uploads").unwrap();
+    let url = upload(&app, &admin, &png(400, 300)).await;
+    let on_disk: u64 = uploaded_files()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|(file, _)| upload_key(file) == upload_key(&url))
+        .map(|(_, metadata)| metadata.len())
+        .sum();
+    let usage = |body: serde_json::Value| {
+        let storage: StorageUsage = serde_json::from_value(body).unwrap();
+        assert!(storage.total_bytes >= on_disk);
+        let usage = storage
+            .users
+            .into_iter()
+            .find(|u| u.id == admin_id)
+            .unwrap();
+        (usage.image_count, usage.bytes)
+    };
+    let (_, body) = send(&app, &admin, "GET", "/api/admin/storage", None).await;
+    assert_eq!(usage(body), (1, on_disk));
+
+    // 7. Uploads from before sizes were recorded are measured from disk
+    sqlx::query("UPDATE uploads SET size = NULL")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let (_, body) = send(&app, &admin, "GET", "/api/admin/storage", None).await;
+    assert_eq!(usage(body), (1, 0));
+
+    for url in maintenance::unmeasured_uploads(&pool).await.unwrap() {
+        maintenance::record_upload_size(&pool, &url).await.unwrap();
+    }
+    let (_, body) = send(&app, &admin, "GET", "/api/admin/storage", None).await;
+    assert_eq!(usage(body), (1, on_disk));
+
+    delete_image(&url).await.unwrap();
+}
+
+// Helpers (Duplicated for isolation as requested)
+fn png(width: u32, height: u32) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    RgbImage::from_pixel(width, height, image::Rgb([200, 120, 40]))
+        .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .unwrap();
+    bytes
+}
+
+async fn upload(app: &axum::Router, token: &str, bytes: &[u8]) -> String {
+    let boundary = "upload-test-boundary";
+    let mut body = format!(
+        "--{boundary}\r\nContent-Disposition: form-data; name=\"image\"; filename=\"photo.png\"\r\nContent-Type: image/png\r\n\r\n"
+    )
+    .into_bytes();
+    body.extend_from_slice(bytes);
+    body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/upload")
+                .method("POST")
+                .header("Authorization", format!("Bearer {}", token))
+                .header(
+                    "Content-Type",
+                    format!("multipart/form-data; boundary={boundary}"),
+                )
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    json["url"].as_str().unwrap().to_string()
+}
+
+async fn create_user(pool: &SqlitePool, username: &str, password: &str) -> i64 {
+    use argon2::{
+        Argon2,
+        password_hash::{PasswordHasher, SaltString, rand_core::OsRng},
+    };
+
+    let salt = SaltString::generate(&mut OsRng);
+    let argon2 = Argon2::default();
+    let password_hash = argon2
+        .hash_password(password.as_bytes(), &salt)
+        .unwrap()
+        .to_string();
+
+    let rec = sqlx::query!(
+        "INSERT INTO users (username, password_hash) VALUES (?, ?) RETURNING id",
+        username,
+        password_hash
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap();
+
+    rec.id
+}
+
+async fn login(app: &axum::Router, username: &str, password: &str) -> Option<String> {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/login")
+                .method("POST")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "username": username,
+                        "password": password
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    if response.status() != StatusCode::OK {
+        return None;
+    }
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    Some(json["token"].as_str().unwrap().to_string())
+}
+
+async fn send(
+    app: &axum::Router,
+    token: &str,
+    method: &str,
+    uri: &str,
+    body: Option<serde_json::Value>,
+) -> (StatusCode, serde_json::Value) {
+    let builder = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("Authorization", format!("Bearer {}", token));
+    let request = match body {
+        Some(body) => builder
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap(),
+        None => builder.body(Body::empty()).unwrap(),
+    };
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json = serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null);
+    (status, json)
+}
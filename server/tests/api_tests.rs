@@ -3,13 +3,18 @@ use axum::{
     http::{Request, StatusCode},
 };
 use serde_json::json;
-use server::{create_router, models::ApiItem};
+use server::{
+    auth::JwtKeys,
+    create_router,
+    models::{ApiItem, CategorySummary},
+    state::AppState,
+};
 use sqlx::SqlitePool;
 use tower::ServiceExt;
 
 #[sqlx::test]
 async fn test_item_lifecycle(pool: SqlitePool) {
-    let app = create_router(pool.clone());
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
     let _user_id = create_user(&pool, "testuser", "password").await;
     let token = login(&app, "testuser", "password").await;
 
@@ -154,7 +159,7 @@ async fn test_item_lifecycle(pool: SqlitePool) {
 
 #[sqlx::test]
 async fn test_categories(pool: SqlitePool) {
-    let app = create_router(pool.clone());
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
     let _ = create_user(&pool, "cat_user", "pass").await;
     let token = login(&app, "cat_user", "pass").await;
 
@@ -198,10 +203,10 @@ async fn test_categories(pool: SqlitePool) {
     let body = axum::body::to_bytes(response.into_body(), usize::MAX)
         .await
         .unwrap();
-    let categories: Vec<String> = serde_json::from_slice(&body).unwrap();
+    let categories: Vec<CategorySummary> = serde_json::from_slice(&body).unwrap();
 
-    assert!(categories.contains(&"Books".to_string()));
-    assert!(categories.contains(&"Movies".to_string()));
+    assert!(categories.iter().any(|c| c.name == "Books"));
+    assert!(categories.iter().any(|c| c.name == "Movies"));
 
     // 2. Filter Items by Category
     let response = app
@@ -229,7 +234,7 @@ async fn test_categories(pool: SqlitePool) {
 
 #[sqlx::test]
 async fn test_sad_paths_and_security(pool: SqlitePool) {
-    let app = create_router(pool.clone());
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
     let _ = create_user(&pool, "victim", "pass").await;
     let _ = create_user(&pool, "hacker", "pass").await;
 
@@ -0,0 +1,568 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use serde_json::json;
+use server::{
+    auth::JwtKeys,
+    create_router,
+    models::{ApiItem, Category, CategorySummary, PublicCategory},
+    state::AppState,
+};
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+
+#[sqlx::test]
+async fn test_rename_category(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    let _ = create_user(&pool, "renamer", "pass").await;
+    let _ = create_user(&pool, "intruder", "pass").await;
+    let token = login(&app, "renamer", "pass").await;
+    let intruder_token = login(&app, "intruder", "pass").await;
+
+    create_item(&app, &token, "Moveis", "Alien").await;
+    create_item(&app, &token, "Books", "Dune").await;
+    let id = category_id(&pool, "Moveis").await;
+
+    // 1. Rename keeps the items attached
+    let response = send(
+        &app,
+        &token,
+        "PATCH",
+        &format!("/api/categories/{}", id),
+        Some(json!({ "name": "Movies" })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let category: Category = serde_json::from_slice(&body).unwrap();
+    assert_eq!(category.id, id);
+    assert_eq!(category.name, "Movies");
+
+    let items = list_items(&app, &token, "Movies").await;
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].name, "Alien");
+
+    // 2. Names stay unique per user and can't be blank
+    let response = send(
+        &app,
+        &token,
+        "PATCH",
+        &format!("/api/categories/{}", id),
+        Some(json!({ "name": "Books" })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let response = send(
+        &app,
+        &token,
+        "PATCH",
+        &format!("/api/categories/{}", id),
+        Some(json!({ "name": "  " })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // 3. Other users can't touch it
+    let response = send(
+        &app,
+        &intruder_token,
+        "PATCH",
+        &format!("/api/categories/{}", id),
+        Some(json!({ "name": "Mine" })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = send(
+        &app,
+        &intruder_token,
+        "DELETE",
+        &format!("/api/categories/{}?delete_items=true", id),
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test]
+async fn test_delete_category_and_items(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    let _ = create_user(&pool, "cleaner", "pass").await;
+    let token = login(&app, "cleaner", "pass").await;
+
+    create_item(&app, &token, "Snacks", "Chips").await;
+    create_item(&app, &token, "Snacks", "Pretzels").await;
+    let id = category_id(&pool, "Snacks").await;
+
+    // 1. A non-empty category isn't deleted by accident
+    let response = send(
+        &app,
+        &token,
+        "DELETE",
+        &format!("/api/categories/{}", id),
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    assert_eq!(list_items(&app, &token, "Snacks").await.len(), 2);
+
+    // 2. Emptying keeps the category
+    let response = send(
+        &app,
+        &token,
+        "DELETE",
+        &format!("/api/categories/{}/items", id),
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert!(list_items(&app, &token, "Snacks").await.is_empty());
+    assert!(
+        list_categories(&app, &token)
+            .await
+            .iter()
+            .any(|c| c.name == "Snacks")
+    );
+
+    // 3. Cascading delete removes the category and everything in it
+    create_item(&app, &token, "Snacks", "Popcorn").await;
+    let response = send(
+        &app,
+        &token,
+        "DELETE",
+        &format!("/api/categories/{}?delete_items=true", id),
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert!(list_items(&app, &token, "Snacks").await.is_empty());
+    assert!(
+        !list_categories(&app, &token)
+            .await
+            .iter()
+            .any(|c| c.name == "Snacks")
+    );
+
+    let response = send(
+        &app,
+        &token,
+        "DELETE",
+        &format!("/api/categories/{}", id),
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test]
+async fn test_category_summaries(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    let _ = create_user(&pool, "summarizer", "pass").await;
+    let _ = create_user(&pool, "bystander", "pass").await;
+    let token = login(&app, "summarizer", "pass").await;
+    let bystander_token = login(&app, "bystander", "pass").await;
+
+    let okay = create_item(&app, &token, "Pizza", "Okay").await;
+    let great = create_item(&app, &token, "Pizza", "Great").await;
+    create_item(&app, &token, "Pizza", "Untried").await;
+    create_item(&app, &token, "Bagels", "Everything").await;
+    set_rank(&app, &token, &okay.id, 100.0).await;
+    set_rank(&app, &token, &great.id, 200.0).await;
+
+    let categories = list_categories(&app, &token).await;
+    let names: Vec<&str> = categories.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, vec!["Bagels", "Pizza"]);
+
+    let pizza = &categories[1];
+    assert_eq!(pizza.id, category_id(&pool, "Pizza").await);
+    assert_eq!(pizza.item_count, 3);
+    assert_eq!(pizza.ranked_count, 2);
+    assert_eq!(pizza.unranked_count, 1);
+    let top_item = pizza.top_item.as_ref().unwrap();
+    assert_eq!(top_item.id, great.id);
+    assert!(top_item.normalized_score.is_some());
+    assert!(pizza.updated_at >= pizza.created_at);
+
+    // Nothing ranked yet, so no top item
+    let bagels = &categories[0];
+    assert_eq!(bagels.item_count, 1);
+    assert_eq!(bagels.unranked_count, 1);
+    assert!(bagels.top_item.is_none());
+
+    // Summaries are per user
+    assert!(list_categories(&app, &bystander_token).await.is_empty());
+}
+
+#[sqlx::test]
+async fn test_category_metadata_and_order(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    let _ = create_user(&pool, "curator", "pass").await;
+    let token = login(&app, "curator", "pass").await;
+
+    // 1. Categories can be created up front with their details
+    let response = send(
+        &app,
+        &token,
+        "POST",
+        "/api/categories",
+        Some(json!({
+            "name": "Ramen",
+            "description": "Best bowls in town",
+            "icon": "🍜"
+        })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let ramen: Category = serde_json::from_slice(&body).unwrap();
+    assert_eq!(ramen.description.as_deref(), Some("Best bowls in town"));
+    assert_eq!(ramen.icon.as_deref(), Some("🍜"));
+    assert!(ramen.position.is_none());
+
+    let response = send(
+        &app,
+        &token,
+        "POST",
+        "/api/categories",
+        Some(json!({ "name": "Ramen" })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let response = send(
+        &app,
+        &token,
+        "POST",
+        "/api/categories",
+        Some(json!({ "name": "Sushi", "icon": "x".repeat(100) })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Items land in the pre-made category
+    create_item(&app, &token, "Ramen", "Shoyu").await;
+    create_item(&app, &token, "Dumplings", "Har Gow").await;
+    create_item(&app, &token, "Curry", "Katsu").await;
+
+    // 2. Details can be edited later
+    let response = send(
+        &app,
+        &token,
+        "PATCH",
+        &format!("/api/categories/{}", ramen.id),
+        Some(json!({ "cover_image_url": "https://example.com/ramen.jpg" })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let updated: Category = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        updated.cover_image_url.as_deref(),
+        Some("https://example.com/ramen.jpg")
+    );
+    assert_eq!(updated.icon.as_deref(), Some("🍜"));
+
+    // 3. Unordered categories list alphabetically
+    let names: Vec<String> = list_categories(&app, &token)
+        .await
+        .into_iter()
+        .map(|c| c.name)
+        .collect();
+    assert_eq!(names, vec!["Curry", "Dumplings", "Ramen"]);
+
+    // 4. Manual order puts listed categories first
+    let dumplings = category_id(&pool, "Dumplings").await;
+    let response = send(
+        &app,
+        &token,
+        "PUT",
+        "/api/categories/order",
+        Some(json!({ "ids": [ramen.id, dumplings] })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let categories = list_categories(&app, &token).await;
+    let names: Vec<&str> = categories.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, vec!["Ramen", "Dumplings", "Curry"]);
+    assert_eq!(
+        categories[0].description.as_deref(),
+        Some("Best bowls in town")
+    );
+    assert_eq!(categories[0].position, Some(0));
+
+    // Unknown ids abort the whole reorder
+    let response = send(
+        &app,
+        &token,
+        "PUT",
+        "/api/categories/order",
+        Some(json!({ "ids": [dumplings, "nope"] })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let names: Vec<String> = list_categories(&app, &token)
+        .await
+        .into_iter()
+        .map(|c| c.name)
+        .collect();
+    assert_eq!(names, vec!["Ramen", "Dumplings", "Curry"]);
+}
+
+#[sqlx::test]
+async fn test_public_share_links(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    let _ = create_user(&pool, "sharer", "pass").await;
+    let token = login(&app, "sharer", "pass").await;
+
+    let ok = create_item(&app, &token, "Ramen", "Ok Ramen").await;
+    let best = create_item(&app, &token, "Ramen", "Best Ramen").await;
+    create_item(&app, &token, "Ramen", "Not Tried Yet").await;
+    set_rank(&app, &token, &ok.id, 100.0).await;
+    set_rank(&app, &token, &best.id, 200.0).await;
+    let id = category_id(&pool, "Ramen").await;
+
+    // 1. Categories start private
+    let categories = list_categories(&app, &token).await;
+    assert!(!categories[0].is_public);
+    assert!(categories[0].share_slug.is_none());
+
+    // 2. Publishing mints a share link that works without logging in
+    let category = publish(&app, &token, &id, true).await;
+    assert!(category.is_public);
+    let slug = category.share_slug.unwrap();
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/public/{}", slug))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let list: PublicCategory = serde_json::from_slice(&body).unwrap();
+    assert_eq!(list.name, "Ramen");
+    let ranking: Vec<(usize, &str)> = list
+        .items
+        .iter()
+        .map(|i| (i.rank, i.name.as_str()))
+        .collect();
+    assert_eq!(ranking, vec![(1, "Best Ramen"), (2, "Ok Ramen")]);
+    assert!(list.items.iter().all(|i| i.normalized_score.is_some()));
+
+    // Re-publishing keeps the same link
+    let category = publish(&app, &token, &id, true).await;
+    assert_eq!(category.share_slug.as_deref(), Some(slug.as_str()));
+
+    // 3. Making it private revokes the link
+    let category = publish(&app, &token, &id, false).await;
+    assert!(category.share_slug.is_none());
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/public/{}", slug))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Private data still needs a token
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/items?category=Ramen")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+// Helpers (Duplicated for isolation as requested)
+async fn create_user(pool: &SqlitePool, username: &str, password: &str) -> i64 {
+    use argon2::{
+        Argon2,
+        password_hash::{PasswordHasher, SaltString, rand_core::OsRng},
+    };
+
+    let salt = SaltString::generate(&mut OsRng);
+    let argon2 = Argon2::default();
+    let password_hash = argon2
+        .hash_password(password.as_bytes(), &salt)
+        .unwrap()
+        .to_string();
+
+    let rec = sqlx::query!(
+        "INSERT INTO users (username, password_hash) VALUES (?, ?) RETURNING id",
+        username,
+        password_hash
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap();
+
+    rec.id
+}
+
+async fn login(app: &axum::Router, username: &str, password: &str) -> String {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/login")
+                .method("POST")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "username": username,
+                        "password": password
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    json["token"].as_str().unwrap().to_string()
+}
+
+async fn create_item(app: &axum::Router, token: &str, category: &str, name: &str) -> ApiItem {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/items")
+                .method("POST")
+                .header("Content-Type", "application/json")
+                .header("Authorization", format!("Bearer {}", token))
+                .body(Body::from(
+                    json!({
+                        "category": category,
+                        "name": name,
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+async fn list_items(app: &axum::Router, token: &str, category: &str) -> Vec<ApiItem> {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/items?category={}", category))
+                .header("Authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+async fn list_categories(app: &axum::Router, token: &str) -> Vec<CategorySummary> {
+    let response = send(app, token, "GET", "/api/categories", None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+async fn set_rank(app: &axum::Router, token: &str, item_id: &str, rank: f64) {
+    let response = send(
+        app,
+        token,
+        "PATCH",
+        &format!("/api/items/{}", item_id),
+        Some(json!({ "rank_order": rank })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+async fn category_id(pool: &SqlitePool, name: &str) -> String {
+    sqlx::query_scalar("SELECT id FROM categories WHERE name = ?")
+        .bind(name)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+async fn send(
+    app: &axum::Router,
+    token: &str,
+    method: &str,
+    uri: &str,
+    body: Option<serde_json::Value>,
+) -> axum::response::Response {
+    let builder = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("Authorization", format!("Bearer {}", token));
+
+    let request = match body {
+        Some(body) => builder
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string())),
+        None => builder.body(Body::empty()),
+    };
+
+    app.clone().oneshot(request.unwrap()).await.unwrap()
+}
+
+async fn publish(app: &axum::Router, token: &str, id: &str, is_public: bool) -> Category {
+    let response = send(
+        app,
+        token,
+        "PATCH",
+        &format!("/api/categories/{}", id),
+        Some(json!({ "is_public": is_public })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
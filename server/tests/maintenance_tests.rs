@@ -0,0 +1,140 @@
+use server::{account::delete_user_data, auth::create_user, maintenance, models::Export};
+use sqlx::SqlitePool;
+
+#[sqlx::test]
+async fn test_export_import_round_trip(pool: SqlitePool) {
+    let alice = create_user(&pool, "alice", "password1", false)
+        .await
+        .unwrap();
+    let bob = create_user(&pool, "bob", "password2", false).await.unwrap();
+    seed_category(&pool, alice, "c1", "Books").await;
+
+    // 1. The export carries items and comparisons
+    let export = maintenance::export_user(&pool, alice).await.unwrap();
+    assert_eq!(export.categories.len(), 1);
+    assert_eq!(export.categories[0].items.len(), 2);
+    assert_eq!(export.categories[0].comparisons.len(), 1);
+
+    // 2. It survives a trip through JSON into another account, with new ids
+    let json = serde_json::to_string(&export).unwrap();
+    let export: Export = serde_json::from_str(&json).unwrap();
+    maintenance::import_user(&pool, bob, &export).await.unwrap();
+
+    let counts = maintenance::category_counts(&pool, Some(bob))
+        .await
+        .unwrap();
+    assert_eq!(counts.len(), 1);
+    assert_eq!(counts[0].name, "Books");
+    assert_eq!(counts[0].item_count, 2);
+    assert_eq!(counts[0].ranked_count, 2);
+
+    let copied = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM comparisons cmp
+         JOIN items w ON cmp.winner_id = w.id
+         WHERE cmp.user_id = ? AND w.user_id = ? AND w.name = 'Winner'",
+    )
+    .bind(bob)
+    .bind(bob)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(copied, 1);
+
+    // 3. Importing the same names twice is refused and changes nothing
+    assert!(maintenance::import_user(&pool, bob, &export).await.is_err());
+    let counts = maintenance::category_counts(&pool, None).await.unwrap();
+    assert_eq!(counts.len(), 2);
+
+    assert!(
+        maintenance::check_integrity(&pool)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[sqlx::test]
+async fn test_delete_user_and_check(pool: SqlitePool) {
+    let alice = create_user(&pool, "alice", "password1", false)
+        .await
+        .unwrap();
+    let bob = create_user(&pool, "bob", "password2", false).await.unwrap();
+    seed_category(&pool, alice, "c1", "Books").await;
+    seed_category(&pool, bob, "c2", "Films").await;
+
+    // 1. A missing image file is reported
+    sqlx::query("UPDATE items SET image_url = '/uploads/missing-file.jpg' WHERE id = 'c1-winner'")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO uploads (url, user_id) VALUES ('/uploads/missing-file.jpg', ?)")
+        .bind(alice)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let problems = maintenance::check_integrity(&pool).await.unwrap();
+    assert_eq!(problems.len(), 1);
+    assert!(problems[0].contains("/uploads/missing-file.jpg"));
+
+    // 2. Deleting alice takes all her rows and hands back her images
+    let mut tx = pool.begin().await.unwrap();
+    let images = delete_user_data(&mut tx, alice).await.unwrap();
+    tx.commit().await.unwrap();
+    assert_eq!(images, vec!["/uploads/missing-file.jpg".to_string()]);
+
+    assert!(maintenance::user_id(&pool, "alice").await.is_err());
+    assert_eq!(maintenance::user_id(&pool, "BOB").await.unwrap(), bob);
+
+    // 3. Bob is untouched, and nothing dangles
+    let counts = maintenance::category_counts(&pool, None).await.unwrap();
+    assert_eq!(counts.len(), 1);
+    assert_eq!(counts[0].username, "bob");
+    assert_eq!(counts[0].item_count, 2);
+    assert!(
+        maintenance::check_integrity(&pool)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+// Helpers (Duplicated for isolation as requested)
+async fn seed_category(pool: &SqlitePool, user_id: i64, id: &str, name: &str) {
+    let winner = format!("{}-winner", id);
+    let loser = format!("{}-loser", id);
+
+    sqlx::query("INSERT INTO categories (id, name, user_id) VALUES (?, ?, ?)")
+        .bind(id)
+        .bind(name)
+        .bind(user_id)
+        .execute(pool)
+        .await
+        .unwrap();
+
+    for (item_id, item_name, rank) in [(&winner, "Winner", 1.0), (&loser, "Loser", 2.0)] {
+        sqlx::query(
+            "INSERT INTO items (id, category_id, user_id, name, rank_order) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(item_id)
+        .bind(id)
+        .bind(user_id)
+        .bind(item_name)
+        .bind(rank)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    sqlx::query(
+        "INSERT INTO comparisons (id, user_id, category_id, winner_id, loser_id)
+         VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(format!("{}-cmp", id))
+    .bind(user_id)
+    .bind(id)
+    .bind(&winner)
+    .bind(&loser)
+    .execute(pool)
+    .await
+    .unwrap();
+}
@@ -0,0 +1,527 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use serde_json::json;
+use server::{
+    auth::JwtKeys,
+    create_router,
+    models::{ApiItem, Comparison, RankSessionResponse},
+    state::AppState,
+};
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+
+#[sqlx::test]
+async fn test_rank_session_binary_insertion(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    let _ = create_user(&pool, "ranker", "pass").await;
+    let token = login(&app, "ranker", "pass").await;
+
+    let good = create_item(&app, &token, "Ramen", "Good").await;
+    let best = create_item(&app, &token, "Ramen", "Best").await;
+    let worst = create_item(&app, &token, "Ramen", "Worst").await;
+
+    // 1. First item in a category has nothing to compare against
+    let session = start_session(&app, &token, &good.id).await;
+    assert!(session.complete);
+    assert!(session.comparison.is_none());
+    assert!(session.item.rank_order.is_some());
+
+    // 2. Second item is compared against the first
+    let session = start_session(&app, &token, &best.id).await;
+    assert!(!session.complete);
+    assert_eq!(session.comparison.as_ref().unwrap().id, good.id);
+
+    // Starting again resumes the same session (e.g. after a page reload)
+    let resumed = start_session(&app, &token, &best.id).await;
+    assert_eq!(resumed.id, session.id);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/rank-sessions/{}", session.id))
+                .header("Authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let session = answer(&app, &token, &session.id, "target").await;
+    assert!(session.complete);
+
+    // 3. Third item loses every comparison and lands at the bottom
+    let mut session = start_session(&app, &token, &worst.id).await;
+    let mut comparisons = 0;
+    while !session.complete {
+        comparisons += 1;
+        session = answer(&app, &token, &session.id, "comparison").await;
+    }
+    assert!(comparisons <= 2);
+
+    // 4. Items come back best to worst
+    let items = list_items(&app, &token, "Ramen").await;
+    let names: Vec<&str> = items.iter().map(|i| i.name.as_str()).collect();
+    assert_eq!(names, vec!["Best", "Good", "Worst"]);
+
+    // 5. Completed sessions are gone
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/rank-sessions/{}", session.id))
+                .header("Authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test]
+async fn test_rank_session_isolation_and_cancel(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    let _ = create_user(&pool, "owner", "pass").await;
+    let _ = create_user(&pool, "other", "pass").await;
+    let owner_token = login(&app, "owner", "pass").await;
+    let other_token = login(&app, "other", "pass").await;
+
+    let first = create_item(&app, &owner_token, "Books", "Dune").await;
+    let second = create_item(&app, &owner_token, "Books", "Emma").await;
+    start_session(&app, &owner_token, &first.id).await;
+    let session = start_session(&app, &owner_token, &second.id).await;
+    assert!(!session.complete);
+
+    // Another user can neither start a session for the item nor answer ours
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/rank-sessions")
+                .method("POST")
+                .header("Content-Type", "application/json")
+                .header("Authorization", format!("Bearer {}", other_token))
+                .body(Body::from(json!({ "item_id": second.id }).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/rank-sessions/{}/answer", session.id))
+                .method("POST")
+                .header("Content-Type", "application/json")
+                .header("Authorization", format!("Bearer {}", other_token))
+                .body(Body::from(json!({ "prefer": "target" }).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Cancelling leaves the item unranked
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/rank-sessions/{}", session.id))
+                .method("DELETE")
+                .header("Authorization", format!("Bearer {}", owner_token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/items/{}", second.id))
+                .header("Authorization", format!("Bearer {}", owner_token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let item: ApiItem = serde_json::from_slice(&body).unwrap();
+    assert!(item.rank_order.is_none());
+}
+
+#[sqlx::test]
+async fn test_rebalance_collapsed_ranks(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    let _ = create_user(&pool, "crowded", "pass").await;
+    let token = login(&app, "crowded", "pass").await;
+
+    let top = create_item(&app, &token, "Coffee", "Top").await;
+    let bottom = create_item(&app, &token, "Coffee", "Bottom").await;
+    let middle = create_item(&app, &token, "Coffee", "Middle").await;
+
+    // 1. Roomy gaps are left alone
+    let top = set_rank(&app, &token, &top.id, 100.0).await;
+    assert_eq!(top.rank_order, Some(100.0));
+    let bottom = set_rank(&app, &token, &bottom.id, 99.999).await;
+    assert_eq!(bottom.rank_order, Some(99.999));
+
+    // 2. Squeezing a third item into a tiny gap renumbers the whole category
+    let middle = set_rank(&app, &token, &middle.id, 99.9995).await;
+    assert_eq!(middle.rank_order, Some(200.0));
+
+    let items = list_items(&app, &token, "Coffee").await;
+    let ranks: Vec<(&str, Option<f64>)> = items
+        .iter()
+        .map(|i| (i.name.as_str(), i.rank_order))
+        .collect();
+    assert_eq!(
+        ranks,
+        vec![
+            ("Top", Some(300.0)),
+            ("Middle", Some(200.0)),
+            ("Bottom", Some(100.0))
+        ]
+    );
+
+    // 3. Explicit rebalance always renumbers
+    set_rank(&app, &token, &top.id, 5000.0).await;
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/categories/{}/rebalance", top.category_id))
+                .method("POST")
+                .header("Authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let result: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(result["renumbered"], 3);
+
+    let items = list_items(&app, &token, "Coffee").await;
+    assert_eq!(items[0].rank_order, Some(300.0));
+
+    // 4. Unknown categories are 404
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/categories/no-such-category/rebalance")
+                .method("POST")
+                .header("Authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test]
+async fn test_rebalance_during_open_session(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    let _ = create_user(&pool, "midway", "pass").await;
+    let token = login(&app, "midway", "pass").await;
+
+    let a = create_item(&app, &token, "Films", "A").await;
+    let b = create_item(&app, &token, "Films", "B").await;
+    let c = create_item(&app, &token, "Films", "C").await;
+    set_rank(&app, &token, &a.id, 1000.0).await;
+    set_rank(&app, &token, &b.id, 500.0).await;
+    set_rank(&app, &token, &c.id, 0.0).await;
+    let x = create_item(&app, &token, "Films", "X").await;
+
+    // 1. X loses to B, so it belongs somewhere below 500
+    let session = start_session(&app, &token, &x.id).await;
+    assert_eq!(session.comparison.unwrap().id, b.id);
+    let session = answer(&app, &token, &session.id, "comparison").await;
+    assert_eq!(session.comparison.unwrap().id, c.id);
+
+    // 2. Renumbering moves A, B and C to 300, 200 and 100
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/categories/{}/rebalance", a.category_id))
+                .method("POST")
+                .header("Authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // 3. The session still only has the room between B and C
+    let session = answer(&app, &token, &session.id, "target").await;
+    assert!(session.complete);
+    let items = list_items(&app, &token, "Films").await;
+    let names: Vec<&str> = items.iter().map(|i| i.name.as_str()).collect();
+    assert_eq!(names, vec!["A", "B", "X", "C"]);
+    assert_eq!(session.item.rank_order, Some(150.0));
+}
+
+#[sqlx::test]
+async fn test_comparisons_are_recorded(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    let _ = create_user(&pool, "auditor", "pass").await;
+    let _ = create_user(&pool, "snoop", "pass").await;
+    let token = login(&app, "auditor", "pass").await;
+    let snoop_token = login(&app, "snoop", "pass").await;
+
+    let tonkotsu = create_item(&app, &token, "Ramen", "Tonkotsu").await;
+    let shoyu = create_item(&app, &token, "Ramen", "Shoyu").await;
+    start_session(&app, &token, &tonkotsu.id).await;
+    let session = start_session(&app, &token, &shoyu.id).await;
+    answer(&app, &token, &session.id, "comparison").await;
+
+    // 1. Both sides of the decision can see it
+    for item_id in [&tonkotsu.id, &shoyu.id] {
+        let comparisons = get_comparisons(&app, &token, item_id).await;
+        assert_eq!(comparisons.len(), 1);
+        assert_eq!(comparisons[0].category, "Ramen");
+        assert_eq!(comparisons[0].winner_id, tonkotsu.id);
+        assert_eq!(comparisons[0].winner_name, "Tonkotsu");
+        assert_eq!(comparisons[0].loser_id, shoyu.id);
+    }
+
+    // 2. Other users cannot read the history
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/items/{}/comparisons", shoyu.id))
+                .header("Authorization", format!("Bearer {}", snoop_token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // 3. Deleting an item drops its comparisons
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/items/{}", shoyu.id))
+                .method("DELETE")
+                .header("Authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert!(get_comparisons(&app, &token, &tonkotsu.id).await.is_empty());
+}
+
+// Helpers (Duplicated for isolation as requested)
+async fn create_user(pool: &SqlitePool, username: &str, password: &str) -> i64 {
+    use argon2::{
+        Argon2,
+        password_hash::{PasswordHasher, SaltString, rand_core::OsRng},
+    };
+
+    let salt = SaltString::generate(&mut OsRng);
+    let argon2 = Argon2::default();
+    let password_hash = argon2
+        .hash_password(password.as_bytes(), &salt)
+        .unwrap()
+        .to_string();
+
+    let rec = sqlx::query!(
+        "INSERT INTO users (username, password_hash) VALUES (?, ?) RETURNING id",
+        username,
+        password_hash
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap();
+
+    rec.id
+}
+
+async fn login(app: &axum::Router, username: &str, password: &str) -> String {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/login")
+                .method("POST")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "username": username,
+                        "password": password
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    json["token"].as_str().unwrap().to_string()
+}
+
+async fn create_item(app: &axum::Router, token: &str, category: &str, name: &str) -> ApiItem {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/items")
+                .method("POST")
+                .header("Content-Type", "application/json")
+                .header("Authorization", format!("Bearer {}", token))
+                .body(Body::from(
+                    json!({
+                        "category": category,
+                        "name": name,
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+async fn start_session(app: &axum::Router, token: &str, item_id: &str) -> RankSessionResponse {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/rank-sessions")
+                .method("POST")
+                .header("Content-Type", "application/json")
+                .header("Authorization", format!("Bearer {}", token))
+                .body(Body::from(json!({ "item_id": item_id }).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+async fn answer(
+    app: &axum::Router,
+    token: &str,
+    session_id: &str,
+    prefer: &str,
+) -> RankSessionResponse {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/rank-sessions/{}/answer", session_id))
+                .method("POST")
+                .header("Content-Type", "application/json")
+                .header("Authorization", format!("Bearer {}", token))
+                .body(Body::from(json!({ "prefer": prefer }).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+async fn set_rank(app: &axum::Router, token: &str, item_id: &str, rank: f64) -> ApiItem {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/items/{}", item_id))
+                .method("PATCH")
+                .header("Content-Type", "application/json")
+                .header("Authorization", format!("Bearer {}", token))
+                .body(Body::from(json!({ "rank_order": rank }).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+async fn list_items(app: &axum::Router, token: &str, category: &str) -> Vec<ApiItem> {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/items?category={}", category))
+                .header("Authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+async fn get_comparisons(app: &axum::Router, token: &str, item_id: &str) -> Vec<Comparison> {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/items/{}/comparisons", item_id))
+                .header("Authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
@@ -0,0 +1,157 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use serde_json::json;
+use server::{auth::JwtKeys, create_router, state::AppState};
+use sqlx::SqlitePool;
+use tower::ServiceExt; // for `oneshot`
+
+#[sqlx::test]
+async fn test_register_with_invite(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    create_invite(&pool, "welcome-1").await;
+
+    // 1. Registering returns a working token
+    let (status, body) = register(&app, "alice", "correct horse", "welcome-1").await;
+    assert_eq!(status, StatusCode::OK);
+    let token = body["token"].as_str().unwrap().to_string();
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/items")
+                .header("Authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // 2. The new account can log in with its password
+    let (status, _) = login(&app, "alice", "correct horse").await;
+    assert_eq!(status, StatusCode::OK);
+
+    // 3. The invite is marked as used by the new account
+    let used_by = sqlx::query_scalar!(
+        "SELECT used_by FROM invite_codes WHERE code = ?",
+        "welcome-1"
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    let user_id = sqlx::query_scalar!("SELECT id FROM users WHERE username = 'alice'")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(used_by, user_id);
+
+    // 4. Invites are single use
+    let (status, _) = register(&app, "bob", "correct horse", "welcome-1").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[sqlx::test]
+async fn test_register_rejections(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    create_invite(&pool, "welcome-1").await;
+    create_invite(&pool, "welcome-2").await;
+
+    // 1. Unknown invite
+    let (status, _) = register(&app, "alice", "correct horse", "nope").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // 2. Invalid usernames and short passwords
+    let (status, _) = register(&app, "al", "correct horse", "welcome-1").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = register(&app, "al ice", "correct horse", "welcome-1").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = register(&app, "alice", "short", "welcome-1").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // 3. Usernames are unique regardless of case
+    let (status, _) = register(&app, "alice", "correct horse", "welcome-1").await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = register(&app, "Alice", "correct horse", "welcome-2").await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // 4. A rejected registration doesn't burn the invite
+    let (status, _) = register(&app, "bob", "correct horse", "welcome-2").await;
+    assert_eq!(status, StatusCode::OK);
+
+    // 5. A used invite is refused without leaving an account behind
+    let (status, _) = register(&app, "carol", "correct horse", "welcome-2").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = login(&app, "carol", "correct horse").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+// Helpers (Duplicated for isolation as requested)
+async fn create_invite(pool: &SqlitePool, code: &str) {
+    sqlx::query!("INSERT INTO invite_codes (code) VALUES (?)", code)
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+async fn register(
+    app: &axum::Router,
+    username: &str,
+    password: &str,
+    invite_code: &str,
+) -> (StatusCode, serde_json::Value) {
+    post_json(
+        app,
+        "/api/register",
+        json!({
+            "username": username,
+            "password": password,
+            "invite_code": invite_code
+        }),
+    )
+    .await
+}
+
+async fn login(
+    app: &axum::Router,
+    username: &str,
+    password: &str,
+) -> (StatusCode, serde_json::Value) {
+    post_json(
+        app,
+        "/api/login",
+        json!({
+            "username": username,
+            "password": password
+        }),
+    )
+    .await
+}
+
+async fn post_json(
+    app: &axum::Router,
+    uri: &str,
+    body: serde_json::Value,
+) -> (StatusCode, serde_json::Value) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(uri)
+                .method("POST")
+                .header("Content-Type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json = serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null);
+    (status, json)
+}
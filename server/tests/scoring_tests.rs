@@ -0,0 +1,340 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use serde_json::json;
+use server::{
+    auth::JwtKeys,
+    create_router,
+    models::{ApiItem, RankSessionResponse},
+    scoring::bradley_terry,
+    state::AppState,
+};
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+
+#[sqlx::test]
+async fn test_bradley_terry_scoring(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    let _ = create_user(&pool, "scorer", "pass").await;
+    let token = login(&app, "scorer", "pass").await;
+
+    let middle = create_item(&app, &token, "Tacos", "Middle").await;
+    let best = create_item(&app, &token, "Tacos", "Best").await;
+    let worst = create_item(&app, &token, "Tacos", "Worst").await;
+    let untried = create_item(&app, &token, "Tacos", "Untried").await;
+
+    // 1. Rank-based categories don't report an interval
+    let items = list_items(&app, &token, "Tacos").await;
+    assert!(items.iter().all(|i| i.score_interval.is_none()));
+
+    let response = set_scoring(&app, &token, &middle.category_id, "bradley_terry").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // 2. Rank through sessions so the comparisons get recorded
+    start_session(&app, &token, &middle.id).await;
+    let mut session = start_session(&app, &token, &best.id).await;
+    while !session.complete {
+        session = answer(&app, &token, &session.id, "target").await;
+    }
+    let mut session = start_session(&app, &token, &worst.id).await;
+    while !session.complete {
+        session = answer(&app, &token, &session.id, "comparison").await;
+    }
+
+    // 3. Scores follow the comparison history and carry an interval
+    let items = list_items(&app, &token, "Tacos").await;
+    let score = |id: &str| {
+        let item = items.iter().find(|i| i.id == id).unwrap();
+        let interval = item.score_interval.unwrap();
+        let score = item.normalized_score.unwrap();
+        assert!(interval.low < score && score < interval.high);
+        score
+    };
+    assert!(score(&best.id) > score(&middle.id));
+    assert!(score(&middle.id) > score(&worst.id));
+
+    // Items that were never compared have nothing to score
+    let untried = items.iter().find(|i| i.id == untried.id).unwrap();
+    assert!(untried.normalized_score.is_none());
+    assert!(untried.score_interval.is_none());
+
+    // 4. Single item lookups use the same model
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/items/{}", best.id))
+                .header("Authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let fetched: ApiItem = serde_json::from_slice(&body).unwrap();
+    assert_eq!(fetched.normalized_score, Some(score(&best.id)));
+
+    // 5. Switching back restores rank-based scores
+    set_scoring(&app, &token, &middle.category_id, "rank").await;
+    let items = list_items(&app, &token, "Tacos").await;
+    assert!(items.iter().all(|i| i.score_interval.is_none()));
+}
+
+#[sqlx::test]
+async fn test_scoring_mode_validation(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    let _ = create_user(&pool, "picky", "pass").await;
+    let _ = create_user(&pool, "meddler", "pass").await;
+    let token = login(&app, "picky", "pass").await;
+    let meddler_token = login(&app, "meddler", "pass").await;
+    let rioja = create_item(&app, &token, "Wine", "Rioja").await;
+
+    let response = set_scoring(&app, &token, &rioja.category_id, "elo").await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let response = set_scoring(&app, &token, "no-such-category", "bradley_terry").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = set_scoring(&app, &meddler_token, &rioja.category_id, "bradley_terry").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn test_bradley_terry_large_sparse_category() {
+    // Each item only ever met its neighbours, as ranking by insertion does
+    let count = 5000;
+    let comparisons: Vec<(usize, usize)> = (1..count).map(|i| (i - 1, i)).collect();
+
+    let strengths = bradley_terry(count, &comparisons);
+    assert_eq!(strengths.len(), count);
+    for strength in &strengths {
+        assert!(strength.interval.low < strength.score && strength.score < strength.interval.high);
+    }
+    assert!(strengths[0].score > strengths[count / 2].score);
+    assert!(strengths[count / 2].score > strengths[count - 1].score);
+}
+
+#[sqlx::test]
+async fn test_rank_scores_are_percentiles(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    let _ = create_user(&pool, "clustered", "pass").await;
+    let token = login(&app, "clustered", "pass").await;
+
+    // Ranks bunched up near zero still spread across the whole scale
+    let low = create_item(&app, &token, "Pho", "Low").await;
+    let mid = create_item(&app, &token, "Pho", "Mid").await;
+    let high = create_item(&app, &token, "Pho", "High").await;
+    let unranked = create_item(&app, &token, "Pho", "Unranked").await;
+    set_rank(&app, &token, &low.id, 0.1).await;
+    set_rank(&app, &token, &mid.id, 0.2).await;
+    let high = set_rank(&app, &token, &high.id, 0.3).await;
+    assert_eq!(high.normalized_score, Some(500.0 / 6.0));
+
+    let items = list_items(&app, &token, "Pho").await;
+    let score = |id: &str| items.iter().find(|i| i.id == id).unwrap().normalized_score;
+    assert_eq!(score(&high.id), Some(500.0 / 6.0));
+    assert_eq!(score(&mid.id), Some(50.0));
+    assert_eq!(score(&low.id), Some(100.0 / 6.0));
+    assert_eq!(score(&unranked.id), None);
+
+    // A lone ranked item sits in the middle
+    let solo = create_item(&app, &token, "Bahn Mi", "Solo").await;
+    let solo = set_rank(&app, &token, &solo.id, 1000.0).await;
+    assert_eq!(solo.normalized_score, Some(50.0));
+}
+
+// Helpers (Duplicated for isolation as requested)
+async fn create_user(pool: &SqlitePool, username: &str, password: &str) -> i64 {
+    use argon2::{
+        Argon2,
+        password_hash::{PasswordHasher, SaltString, rand_core::OsRng},
+    };
+
+    let salt = SaltString::generate(&mut OsRng);
+    let argon2 = Argon2::default();
+    let password_hash = argon2
+        .hash_password(password.as_bytes(), &salt)
+        .unwrap()
+        .to_string();
+
+    let rec = sqlx::query!(
+        "INSERT INTO users (username, password_hash) VALUES (?, ?) RETURNING id",
+        username,
+        password_hash
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap();
+
+    rec.id
+}
+
+async fn login(app: &axum::Router, username: &str, password: &str) -> String {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/login")
+                .method("POST")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "username": username,
+                        "password": password
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    json["token"].as_str().unwrap().to_string()
+}
+
+async fn create_item(app: &axum::Router, token: &str, category: &str, name: &str) -> ApiItem {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/items")
+                .method("POST")
+                .header("Content-Type", "application/json")
+                .header("Authorization", format!("Bearer {}", token))
+                .body(Body::from(
+                    json!({
+                        "category": category,
+                        "name": name,
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+async fn start_session(app: &axum::Router, token: &str, item_id: &str) -> RankSessionResponse {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/rank-sessions")
+                .method("POST")
+                .header("Content-Type", "application/json")
+                .header("Authorization", format!("Bearer {}", token))
+                .body(Body::from(json!({ "item_id": item_id }).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+async fn answer(
+    app: &axum::Router,
+    token: &str,
+    session_id: &str,
+    prefer: &str,
+) -> RankSessionResponse {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/rank-sessions/{}/answer", session_id))
+                .method("POST")
+                .header("Content-Type", "application/json")
+                .header("Authorization", format!("Bearer {}", token))
+                .body(Body::from(json!({ "prefer": prefer }).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+async fn list_items(app: &axum::Router, token: &str, category: &str) -> Vec<ApiItem> {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/items?category={}", category))
+                .header("Authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+async fn set_rank(app: &axum::Router, token: &str, item_id: &str, rank: f64) -> ApiItem {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/items/{}", item_id))
+                .method("PATCH")
+                .header("Content-Type", "application/json")
+                .header("Authorization", format!("Bearer {}", token))
+                .body(Body::from(json!({ "rank_order": rank }).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+async fn set_scoring(
+    app: &axum::Router,
+    token: &str,
+    category_id: &str,
+    mode: &str,
+) -> axum::response::Response {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/categories/{}", category_id))
+                .method("PATCH")
+                .header("Content-Type", "application/json")
+                .header("Authorization", format!("Bearer {}", token))
+                .body(Body::from(json!({ "scoring_mode": mode }).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
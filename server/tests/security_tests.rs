@@ -3,14 +3,14 @@ use axum::{
     http::{Request, StatusCode},
 };
 use serde_json::json;
-use server::{create_router, models::ApiItem}; // Assuming lib.rs exposes create_router and models
+use server::{auth::JwtKeys, create_router, models::ApiItem, state::AppState}; // Assuming lib.rs exposes create_router and models
 use sqlx::SqlitePool;
 use tower::ServiceExt; // for `oneshot`
 
 #[sqlx::test]
 async fn test_auth_and_isolation(pool: SqlitePool) {
     // 1. Setup App
-    let app = create_router(pool.clone());
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
 
     // 2. Create Users
     // We can interact with DB directly for setup to avoid spinning up full registration flow (which we don't have)
@@ -104,6 +104,141 @@ async fn test_auth_and_isolation(pool: SqlitePool) {
     assert_eq!(response.status(), StatusCode::NOT_FOUND); // OR 403, but our logic returns 404 for "not found in my list"
 }
 
+#[sqlx::test]
+async fn test_jwt_key_rotation(pool: SqlitePool) {
+    let old_secret = "an-old-secret-that-is-long-enough-to-use";
+    let new_secret = "a-new-secret-that-is-also-long-enough-to-use";
+    create_user(&pool, "user1", "pass1").await;
+
+    let before = create_router(AppState::new(
+        pool.clone(),
+        JwtKeys::new(&[("2026-01", old_secret)]).unwrap(),
+    ));
+    let during = create_router(AppState::new(
+        pool.clone(),
+        JwtKeys::new(&[("2026-02", new_secret), ("2026-01", old_secret)]).unwrap(),
+    ));
+    let after = create_router(AppState::new(
+        pool.clone(),
+        JwtKeys::new(&[("2026-02", new_secret)]).unwrap(),
+    ));
+
+    // 1. Tokens signed with the old key keep working while it is still listed
+    let old_token = login(&before, "user1", "pass1").await;
+    assert_eq!(items_status(&during, &old_token).await, StatusCode::OK);
+
+    // 2. New tokens are signed with the new key
+    let new_token = login(&during, "user1", "pass1").await;
+    assert_eq!(items_status(&during, &new_token).await, StatusCode::OK);
+    assert_eq!(items_status(&after, &new_token).await, StatusCode::OK);
+    assert_eq!(
+        items_status(&before, &new_token).await,
+        StatusCode::UNAUTHORIZED
+    );
+
+    // 3. Once the old key is retired its tokens are refused
+    assert_eq!(
+        items_status(&after, &old_token).await,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[test]
+fn test_jwt_config_fails_closed() {
+    let strong = "0123456789abcdef0123456789abcdef";
+
+    // 1. No secret, or a weak one, only passes in development
+    assert!(JwtKeys::from_config(None, None, false).is_err());
+    assert!(JwtKeys::from_config(None, Some("secret"), false).is_err());
+    assert!(JwtKeys::from_config(None, None, true).is_ok());
+    assert!(JwtKeys::from_config(None, Some("secret"), true).is_ok());
+    assert!(JwtKeys::from_config(None, Some(strong), false).is_ok());
+
+    // 2. Every key in a rotation list must be well formed and strong
+    let rotation = format!("new:{}, old:{}", strong, strong);
+    assert!(JwtKeys::from_config(Some(&rotation), None, false).is_ok());
+    assert!(
+        JwtKeys::from_config(Some(&format!("new:{}, old:short", strong)), None, false).is_err()
+    );
+    assert!(JwtKeys::from_config(Some(strong), None, false).is_err());
+    assert!(
+        JwtKeys::from_config(Some(&format!("k:{}, k:{}", strong, strong)), None, false).is_err()
+    );
+}
+
+#[sqlx::test]
+async fn test_login_backoff_and_lockout(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    create_user(&pool, "user1", "pass1").await;
+
+    // 1. A few mistakes are free
+    for _ in 0..2 {
+        let status = try_login(&app, "user1", "wrong", "10.0.0.1").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    // 2. Then the username has to wait, even with the right password and from elsewhere
+    let status = try_login(&app, "user1", "wrong", "10.0.0.1").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let status = try_login(&app, "user1", "pass1", "10.0.0.2").await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    let status = try_login(&app, "USER1", "pass1", "10.0.0.2").await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+
+    // 3. The wait doubles with every further failure
+    let mut waits = Vec::new();
+    for _ in 0..3 {
+        expire_blocks(&pool).await;
+        let status = try_login(&app, "user1", "wrong", "10.0.0.1").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        waits.push(block_secs(&pool, "user:user1").await);
+    }
+    assert_eq!(waits, vec![2, 4, 8]);
+
+    // 4. Enough failures lock the account for a while
+    for _ in 0..4 {
+        expire_blocks(&pool).await;
+        try_login(&app, "user1", "wrong", "10.0.0.1").await;
+    }
+    assert_eq!(block_secs(&pool, "user:user1").await, 15 * 60);
+
+    // 5. Once it lifts, a successful login resets the count
+    expire_blocks(&pool).await;
+    let status = try_login(&app, "user1", "pass1", "10.0.0.1").await;
+    assert_eq!(status, StatusCode::OK);
+
+    // (10.0.0.1 itself is now over the per-address limit)
+    let status = try_login(&app, "user1", "wrong", "10.0.0.3").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let status = try_login(&app, "user1", "pass1", "10.0.0.3").await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[sqlx::test]
+async fn test_login_throttled_per_ip(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    create_user(&pool, "user1", "pass1").await;
+
+    // 1. Guessing across many usernames (real or not) trips the per-address limit
+    for i in 0..10 {
+        let status = try_login(&app, &format!("guess{}", i), "wrong", "10.0.0.66").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+    let status = try_login(&app, "user1", "pass1", "10.0.0.66").await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+
+    // 2. Other addresses are unaffected
+    let status = try_login(&app, "user1", "pass1", "10.0.0.7").await;
+    assert_eq!(status, StatusCode::OK);
+
+    // 3. Unknown usernames are throttled just like real ones
+    for _ in 0..3 {
+        try_login(&app, "nobody", "wrong", "10.0.0.8").await;
+    }
+    let status = try_login(&app, "nobody", "wrong", "10.0.0.9").await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+}
+
 // Helpers
 
 async fn create_user(pool: &SqlitePool, username: &str, password: &str) -> i64 {
@@ -163,3 +298,62 @@ async fn login(app: &axum::Router, username: &str, password: &str) -> String {
     let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
     json["token"].as_str().unwrap().to_string()
 }
+
+async fn items_status(app: &axum::Router, token: &str) -> StatusCode {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/items")
+                .header("Authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    response.status()
+}
+
+async fn try_login(app: &axum::Router, username: &str, password: &str, ip: &str) -> StatusCode {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/login")
+                .method("POST")
+                .header("Content-Type", "application/json")
+                .header("X-Real-IP", ip)
+                .body(Body::from(
+                    json!({
+                        "username": username,
+                        "password": password
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    response.status()
+}
+
+// Stands in for waiting out the current backoff
+async fn expire_blocks(pool: &SqlitePool) {
+    sqlx::query!("UPDATE login_throttle SET blocked_until = datetime('now', '-1 second')")
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+async fn block_secs(pool: &SqlitePool, key: &str) -> i64 {
+    sqlx::query_scalar!(
+        r#"SELECT CAST(strftime('%s', blocked_until) - strftime('%s', last_failure_at) AS INTEGER) as "secs!: i64"
+         FROM login_throttle WHERE key = ?"#,
+        key
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
@@ -0,0 +1,224 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use serde_json::json;
+use server::{auth::JwtKeys, create_router, models::SessionInfo, state::AppState};
+use sqlx::SqlitePool;
+use tower::ServiceExt; // for `oneshot`
+
+#[sqlx::test]
+async fn test_refresh_rotation(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    create_user(&pool, "alice", "password1").await;
+    let first = login(&app, "alice", "password1").await;
+
+    // 1. A refresh token buys a new pair of tokens
+    let (status, body) = refresh(&app, &first.refresh).await;
+    assert_eq!(status, StatusCode::OK);
+    let second = Tokens::from_json(&body);
+    assert_ne!(second.refresh, first.refresh);
+
+    let (status, _) = send(&app, &second.access, "GET", "/api/items", None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // 2. The same session carries on
+    let (status, body) = send(&app, &second.access, "GET", "/api/sessions", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let sessions: Vec<SessionInfo> = serde_json::from_value(body).unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].current);
+
+    // 3. Replaying a spent refresh token ends the session for everyone
+    let (status, _) = refresh(&app, &first.refresh).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = refresh(&app, &second.refresh).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = send(&app, &second.access, "GET", "/api/items", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // 4. Garbage is refused
+    let (status, _) = refresh(&app, "not-a-token").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // 5. A disabled account can't refresh, even with its sessions left in place
+    let third = login(&app, "alice", "password1").await;
+    sqlx::query!("UPDATE users SET disabled_at = CURRENT_TIMESTAMP WHERE username = 'alice'")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let (status, _) = refresh(&app, &third.refresh).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[sqlx::test]
+async fn test_logout_and_revoke(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    create_user(&pool, "alice", "password1").await;
+    create_user(&pool, "bob", "password2").await;
+    let laptop = login(&app, "alice", "password1").await;
+    let phone = login(&app, "alice", "password1").await;
+    let tablet = login(&app, "alice", "password1").await;
+    let bob = login(&app, "bob", "password2").await;
+
+    // 1. Each login is its own session
+    let (_, body) = send(&app, &laptop.access, "GET", "/api/sessions", None).await;
+    let sessions: Vec<SessionInfo> = serde_json::from_value(body).unwrap();
+    assert_eq!(sessions.len(), 3);
+    assert_eq!(sessions.iter().filter(|s| s.current).count(), 1);
+
+    // 2. Logging out kills the access and refresh token of that session only
+    let (status, _) = send(&app, &tablet.access, "POST", "/api/logout", None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send(&app, &tablet.access, "GET", "/api/items", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = refresh(&app, &tablet.refresh).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // 3. Another device can be revoked by id, but not someone else's
+    let (_, body) = send(&app, &phone.access, "GET", "/api/sessions", None).await;
+    let sessions: Vec<SessionInfo> = serde_json::from_value(body).unwrap();
+    assert_eq!(sessions.len(), 2);
+    let laptop_session = sessions.iter().find(|s| !s.current).unwrap().id.clone();
+
+    let uri = format!("/api/sessions/{}", laptop_session);
+    let (status, _) = send(&app, &bob.access, "DELETE", &uri, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send(&app, &phone.access, "DELETE", &uri, None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send(&app, &laptop.access, "GET", "/api/items", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // 4. Signing out everywhere else keeps the caller's session
+    let desktop = login(&app, "alice", "password1").await;
+    let (status, _) = send(&app, &phone.access, "DELETE", "/api/sessions", None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send(&app, &desktop.access, "GET", "/api/items", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = send(&app, &phone.access, "GET", "/api/items", None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // 5. Bob was never affected
+    let (status, _) = send(&app, &bob.access, "GET", "/api/items", None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+// Helpers (Duplicated for isolation as requested)
+async fn create_user(pool: &SqlitePool, username: &str, password: &str) -> i64 {
+    use argon2::{
+        Argon2,
+        password_hash::{PasswordHasher, SaltString, rand_core::OsRng},
+    };
+
+    let salt = SaltString::generate(&mut OsRng);
+    let argon2 = Argon2::default();
+    let password_hash = argon2
+        .hash_password(password.as_bytes(), &salt)
+        .unwrap()
+        .to_string();
+
+    let rec = sqlx::query!(
+        "INSERT INTO users (username, password_hash) VALUES (?, ?) RETURNING id",
+        username,
+        password_hash
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap();
+
+    rec.id
+}
+
+async fn login(app: &axum::Router, username: &str, password: &str) -> Tokens {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/login")
+                .method("POST")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "username": username,
+                        "password": password
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    Tokens::from_json(&json)
+}
+
+struct Tokens {
+    access: String,
+    refresh: String,
+}
+
+impl Tokens {
+    fn from_json(json: &serde_json::Value) -> Self {
+        Tokens {
+            access: json["token"].as_str().unwrap().to_string(),
+            refresh: json["refresh_token"].as_str().unwrap().to_string(),
+        }
+    }
+}
+
+async fn refresh(app: &axum::Router, refresh_token: &str) -> (StatusCode, serde_json::Value) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/token/refresh")
+                .method("POST")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    json!({ "refresh_token": refresh_token }).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json = serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null);
+    (status, json)
+}
+
+async fn send(
+    app: &axum::Router,
+    token: &str,
+    method: &str,
+    uri: &str,
+    body: Option<serde_json::Value>,
+) -> (StatusCode, serde_json::Value) {
+    let builder = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("Authorization", format!("Bearer {}", token));
+    let request = match body {
+        Some(body) => builder
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap(),
+        None => builder.body(Body::empty()).unwrap(),
+    };
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json = serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null);
+    (status, json)
+}
@@ -0,0 +1,536 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use serde_json::json;
+use server::{auth::JwtKeys, create_router, state::AppState};
+use sqlx::SqlitePool;
+use tower::ServiceExt; // for `oneshot`
+
+#[sqlx::test]
+async fn test_member_roles(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    create_user(&pool, "alice", "password1").await;
+    let bob_id = create_user(&pool, "bob", "password2").await;
+    create_user(&pool, "carol", "password3").await;
+    let alice = login(&app, "alice", "password1").await.unwrap();
+    let bob = login(&app, "bob", "password2").await.unwrap();
+    let carol = login(&app, "carol", "password3").await.unwrap();
+
+    let (_, body) = send(
+        &app,
+        &alice,
+        "POST",
+        "/api/categories",
+        Some(json!({ "name": "Groceries" })),
+    )
+    .await;
+    let category_id = body["id"].as_str().unwrap().to_string();
+    let (_, body) = send(
+        &app,
+        &alice,
+        "POST",
+        "/api/items",
+        Some(json!({ "category": "Groceries", "name": "Milk" })),
+    )
+    .await;
+    let milk = format!("/api/items/{}", body["id"].as_str().unwrap());
+    let members = format!("/api/categories/{}/members", category_id);
+
+    // 1. Only the owner shares, and only with existing users
+    let (status, _) = send(
+        &app,
+        &bob,
+        "POST",
+        &members,
+        Some(json!({ "username": "bob", "role": "editor" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = send(
+        &app,
+        &alice,
+        "POST",
+        &members,
+        Some(json!({ "username": "nobody", "role": "viewer" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, body) = send(
+        &app,
+        &alice,
+        "POST",
+        &members,
+        Some(json!({ "username": "Bob", "role": "viewer" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["username"], "bob");
+    assert_eq!(body["role"], "viewer");
+
+    let (status, _) = send(
+        &app,
+        &alice,
+        "POST",
+        &members,
+        Some(json!({ "username": "bob", "role": "editor" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // 2. A viewer sees the category and its items but can't change them
+    let (status, body) = send(&app, &bob, "GET", "/api/categories", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body[0]["id"], category_id.as_str());
+    assert_eq!(body[0]["role"], "viewer");
+    assert_eq!(body[0]["item_count"], 1);
+
+    let (status, body) = send(&app, &bob, "GET", "/api/items?category=Groceries", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body[0]["name"], "Milk");
+
+    let (status, _) = send(&app, &bob, "GET", &milk, None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = send(
+        &app,
+        &bob,
+        "PATCH",
+        &milk,
+        Some(json!({ "name": "Oat milk" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = send(
+        &app,
+        &bob,
+        "POST",
+        "/api/items",
+        Some(json!({ "category": "Groceries", "category_id": category_id, "name": "Eggs" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, body) = send(&app, &bob, "GET", &members, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body[0]["username"], "alice");
+    assert_eq!(body[0]["role"], "owner");
+    assert_eq!(body[1]["username"], "bob");
+
+    // 3. Outsiders can't tell the category exists
+    let (status, _) = send(&app, &carol, "GET", &milk, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send(&app, &carol, "GET", &members, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (_, body) = send(&app, &carol, "GET", "/api/categories", None).await;
+    assert_eq!(body.as_array().unwrap().len(), 0);
+
+    // 4. An editor adds, edits and ranks items
+    let member = format!("{}/{}", members, bob_id);
+    let (status, body) = send(
+        &app,
+        &alice,
+        "PATCH",
+        &member,
+        Some(json!({ "role": "editor" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["role"], "editor");
+
+    let (status, body) = send(
+        &app,
+        &bob,
+        "POST",
+        "/api/items",
+        Some(json!({ "category": "Groceries", "name": "Eggs" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["category_id"], category_id.as_str());
+    let eggs_id = body["id"].as_str().unwrap().to_string();
+
+    let (status, _) = send(
+        &app,
+        &bob,
+        "PATCH",
+        &milk,
+        Some(json!({ "name": "Oat milk" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = send(
+        &app,
+        &bob,
+        "POST",
+        "/api/rank-sessions",
+        Some(json!({ "item_id": eggs_id })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["complete"], true);
+
+    let (_, body) = send(&app, &alice, "GET", "/api/items?category=Groceries", None).await;
+    assert_eq!(body.as_array().unwrap().len(), 2);
+    assert_eq!(body[0]["name"], "Eggs");
+
+    // 5. The category itself stays the owner's
+    let category = format!("/api/categories/{}", category_id);
+    let (status, _) = send(
+        &app,
+        &bob,
+        "PATCH",
+        &category,
+        Some(json!({ "name": "Food" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send(&app, &bob, "DELETE", &category, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send(
+        &app,
+        &bob,
+        "PATCH",
+        &category,
+        Some(json!({ "scoring_mode": "bradley_terry" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send(
+        &app,
+        &bob,
+        "POST",
+        &members,
+        Some(json!({ "username": "carol", "role": "viewer" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Each member keeps their own order
+    let (status, body) = send(
+        &app,
+        &bob,
+        "PATCH",
+        &category,
+        Some(json!({ "position": 3 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["position"], 3);
+    let (_, body) = send(&app, &alice, "GET", "/api/categories", None).await;
+    assert_eq!(body[0]["position"], serde_json::Value::Null);
+}
+
+#[sqlx::test]
+async fn test_shared_category_names(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    create_user(&pool, "alice", "password1").await;
+    create_user(&pool, "bob", "password2").await;
+    create_user(&pool, "carol", "password3").await;
+    let alice = login(&app, "alice", "password1").await.unwrap();
+    let bob = login(&app, "bob", "password2").await.unwrap();
+    let carol = login(&app, "carol", "password3").await.unwrap();
+
+    let (_, body) = send(
+        &app,
+        &alice,
+        "POST",
+        "/api/categories",
+        Some(json!({ "name": "Books" })),
+    )
+    .await;
+    let shared_id = body["id"].as_str().unwrap().to_string();
+    send(
+        &app,
+        &alice,
+        "POST",
+        &format!("/api/categories/{}/members", shared_id),
+        Some(json!({ "username": "bob", "role": "editor" })),
+    )
+    .await;
+
+    // 1. Without a category of his own, the name finds the shared one
+    let (_, body) = send(
+        &app,
+        &bob,
+        "POST",
+        "/api/items",
+        Some(json!({ "category": "Books", "name": "Dune" })),
+    )
+    .await;
+    assert_eq!(body["category_id"], shared_id.as_str());
+
+    // 2. Once he has one, his own wins; the id still reaches the shared one
+    let (_, body) = send(
+        &app,
+        &bob,
+        "POST",
+        "/api/categories",
+        Some(json!({ "name": "Books" })),
+    )
+    .await;
+    let own_id = body["id"].as_str().unwrap().to_string();
+
+    let (_, body) = send(
+        &app,
+        &bob,
+        "POST",
+        "/api/items",
+        Some(json!({ "category": "Books", "name": "Emma" })),
+    )
+    .await;
+    assert_eq!(body["category_id"], own_id.as_str());
+
+    let (_, body) = send(
+        &app,
+        &bob,
+        "POST",
+        "/api/items",
+        Some(json!({ "category": "Books", "category_id": shared_id, "name": "Ulysses" })),
+    )
+    .await;
+    assert_eq!(body["category_id"], shared_id.as_str());
+
+    let (_, body) = send(
+        &app,
+        &bob,
+        "GET",
+        &format!("/api/items?category_id={}", shared_id),
+        None,
+    )
+    .await;
+    assert_eq!(body.as_array().unwrap().len(), 2);
+
+    // 3. Alice only ever sees her own list
+    let (_, body) = send(&app, &alice, "GET", "/api/items", None).await;
+    assert_eq!(body.as_array().unwrap().len(), 2);
+
+    // 4. A viewer adding to the name gets a category of her own instead
+    send(
+        &app,
+        &alice,
+        "POST",
+        &format!("/api/categories/{}/members", shared_id),
+        Some(json!({ "username": "carol", "role": "viewer" })),
+    )
+    .await;
+    let (status, body) = send(
+        &app,
+        &carol,
+        "POST",
+        "/api/items",
+        Some(json!({ "category": "Books", "name": "Middlemarch" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_ne!(body["category_id"], shared_id.as_str());
+    let (_, body) = send(&app, &carol, "GET", "/api/categories", None).await;
+    let roles: Vec<&str> = body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["role"].as_str().unwrap())
+        .collect();
+    assert_eq!(roles.len(), 2);
+    assert!(roles.contains(&"owner") && roles.contains(&"viewer"));
+
+    // 5. Members are listed owner first, then by name
+    create_user(&pool, "adam", "password4").await;
+    let members = format!("/api/categories/{}/members", shared_id);
+    send(
+        &app,
+        &alice,
+        "POST",
+        &members,
+        Some(json!({ "username": "adam", "role": "viewer" })),
+    )
+    .await;
+    let (_, body) = send(&app, &carol, "GET", &members, None).await;
+    let names: Vec<&str> = body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["username"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["alice", "adam", "bob", "carol"]);
+
+    // 6. Scoring and rebalancing go by id, so both "Books" can be reached
+    let own = format!("/api/categories/{}", own_id);
+    let shared = format!("/api/categories/{}", shared_id);
+    let scoring = json!({ "scoring_mode": "bradley_terry" });
+    let (status, body) = send(&app, &bob, "PATCH", &own, Some(scoring.clone())).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["scoring_mode"], "bradley_terry");
+    let (status, _) = send(&app, &bob, "PATCH", &shared, Some(scoring.clone())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, body) = send(&app, &alice, "PATCH", &shared, Some(scoring)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["id"], shared_id.as_str());
+
+    let (status, body) = send(&app, &bob, "POST", &format!("{}/rebalance", shared), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["category_id"], shared_id.as_str());
+    let (status, _) = send(&app, &carol, "POST", &format!("{}/rebalance", shared), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[sqlx::test]
+async fn test_leave_category(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    let alice_id = create_user(&pool, "alice", "password1").await;
+    let bob_id = create_user(&pool, "bob", "password2").await;
+    let alice = login(&app, "alice", "password1").await.unwrap();
+    let bob = login(&app, "bob", "password2").await.unwrap();
+
+    let (_, body) = send(
+        &app,
+        &alice,
+        "POST",
+        "/api/categories",
+        Some(json!({ "name": "Chores" })),
+    )
+    .await;
+    let category_id = body["id"].as_str().unwrap().to_string();
+    let members = format!("/api/categories/{}/members", category_id);
+    send(
+        &app,
+        &alice,
+        "POST",
+        &members,
+        Some(json!({ "username": "bob", "role": "editor" })),
+    )
+    .await;
+    let (_, body) = send(
+        &app,
+        &bob,
+        "POST",
+        "/api/items",
+        Some(json!({ "category_id": category_id, "category": "Chores", "name": "Dishes" })),
+    )
+    .await;
+    let item = format!("/api/items/{}", body["id"].as_str().unwrap());
+
+    // 1. Members can leave on their own
+    let (status, _) = send(
+        &app,
+        &bob,
+        "DELETE",
+        &format!("{}/{}", members, bob_id),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (status, _) = send(&app, &bob, "GET", &item, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (_, body) = send(&app, &bob, "GET", "/api/categories", None).await;
+    assert_eq!(body.as_array().unwrap().len(), 0);
+
+    // 2. What they added stays with the owner
+    let (status, body) = send(&app, &alice, "GET", &item, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["name"], "Dishes");
+    let owner = sqlx::query_scalar::<_, i64>("SELECT user_id FROM items WHERE name = 'Dishes'")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(owner, alice_id);
+
+    let (status, _) = send(
+        &app,
+        &bob,
+        "DELETE",
+        &format!("{}/{}", members, bob_id),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+// Helpers (Duplicated for isolation as requested)
+async fn create_user(pool: &SqlitePool, username: &str, password: &str) -> i64 {
+    use argon2::{
+        Argon2,
+        password_hash::{PasswordHasher, SaltString, rand_core::OsRng},
+    };
+
+    let salt = SaltString::generate(&mut OsRng);
+    let argon2 = Argon2::default();
+    let password_hash = argon2
+        .hash_password(password.as_bytes(), &salt)
+        .unwrap()
+        .to_string();
+
+    let rec = sqlx::query!(
+        "INSERT INTO users (username, password_hash) VALUES (?, ?) RETURNING id",
+        username,
+        password_hash
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap();
+
+    rec.id
+}
+
+async fn login(app: &axum::Router, username: &str, password: &str) -> Option<String> {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/login")
+                .method("POST")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "username": username,
+                        "password": password
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    if response.status() != StatusCode::OK {
+        return None;
+    }
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    Some(json["token"].as_str().unwrap().to_string())
+}
+
+async fn send(
+    app: &axum::Router,
+    token: &str,
+    method: &str,
+    uri: &str,
+    body: Option<serde_json::Value>,
+) -> (StatusCode, serde_json::Value) {
+    let builder = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("Authorization", format!("Bearer {}", token));
+    let request = match body {
+        Some(body) => builder
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap(),
+        None => builder.body(Body::empty()).unwrap(),
+    };
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json = serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null);
+    (status, json)
+}
@@ -0,0 +1,226 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use serde_json::json;
+use server::{
+    auth::JwtKeys,
+    create_router,
+    models::{AccessToken, ApiItem, CreatedAccessToken, TokenScope},
+    state::AppState,
+};
+use sqlx::SqlitePool;
+use tower::ServiceExt; // for `oneshot`
+
+#[sqlx::test]
+async fn test_access_token_scopes(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    create_user(&pool, "alice", "password1").await;
+    let session = login(&app, "alice", "password1").await;
+
+    // 1. Tokens default to read-only and are only shown once
+    let (status, body) = send(
+        &app,
+        &session,
+        "POST",
+        "/api/tokens",
+        Some(json!({ "name": "backup script" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let read_only: CreatedAccessToken = serde_json::from_value(body).unwrap();
+    assert_eq!(read_only.info.scope, TokenScope::Read);
+
+    let (status, body) = send(
+        &app,
+        &session,
+        "POST",
+        "/api/tokens",
+        Some(json!({ "name": "importer", "scope": "read_write" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let read_write: CreatedAccessToken = serde_json::from_value(body).unwrap();
+
+    let (status, _) = send(
+        &app,
+        &session,
+        "POST",
+        "/api/tokens",
+        Some(json!({ "name": "  " })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // 2. A read-write token can create items
+    let (status, body) = send(
+        &app,
+        &read_write.token,
+        "POST",
+        "/api/items",
+        Some(json!({ "category": "Ramen", "name": "Tonkotsu" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let item: ApiItem = serde_json::from_value(body).unwrap();
+
+    // 3. A read-only token can read but not write
+    let (status, body) = send(&app, &read_only.token, "GET", "/api/items", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let items: Vec<ApiItem> = serde_json::from_value(body).unwrap();
+    assert_eq!(items.len(), 1);
+
+    let uri = format!("/api/items/{}", item.id);
+    let (status, _) = send(&app, &read_only.token, "DELETE", &uri, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // 4. No token can manage tokens, sessions or the account
+    let (status, _) = send(
+        &app,
+        &read_write.token,
+        "POST",
+        "/api/tokens",
+        Some(json!({ "name": "sneaky" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send(&app, &read_write.token, "GET", "/api/sessions", None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // 5. Use is recorded and the secret never listed
+    let (status, body) = send(&app, &session, "GET", "/api/tokens", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!body.to_string().contains(&read_write.token));
+    let tokens: Vec<AccessToken> = serde_json::from_value(body).unwrap();
+    assert_eq!(tokens.len(), 2);
+    assert!(tokens.iter().all(|t| t.last_used_at.is_some()));
+
+    // 6. Tokens are stored hashed
+    let stored = sqlx::query_scalar!("SELECT token_hash FROM personal_access_tokens")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert!(!stored.contains(&read_write.token));
+}
+
+#[sqlx::test]
+async fn test_revoke_access_token(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    create_user(&pool, "alice", "password1").await;
+    create_user(&pool, "bob", "password2").await;
+    let alice = login(&app, "alice", "password1").await;
+    let bob = login(&app, "bob", "password2").await;
+
+    let (_, body) = send(
+        &app,
+        &alice,
+        "POST",
+        "/api/tokens",
+        Some(json!({ "name": "cron" })),
+    )
+    .await;
+    let token: CreatedAccessToken = serde_json::from_value(body).unwrap();
+
+    // 1. Tokens are private to their owner
+    let (_, body) = send(&app, &bob, "GET", "/api/tokens", None).await;
+    let tokens: Vec<AccessToken> = serde_json::from_value(body).unwrap();
+    assert!(tokens.is_empty());
+
+    let uri = format!("/api/tokens/{}", token.info.id);
+    let (status, _) = send(&app, &bob, "DELETE", &uri, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // 2. Revoked tokens stop working
+    let (status, _) = send(&app, &token.token, "GET", "/api/items", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(&app, &alice, "DELETE", &uri, None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send(&app, &token.token, "GET", "/api/items", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // 3. Made-up tokens are refused
+    let (status, _) = send(&app, "stpat_nope", "GET", "/api/items", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+// Helpers (Duplicated for isolation as requested)
+async fn create_user(pool: &SqlitePool, username: &str, password: &str) -> i64 {
+    use argon2::{
+        Argon2,
+        password_hash::{PasswordHasher, SaltString, rand_core::OsRng},
+    };
+
+    let salt = SaltString::generate(&mut OsRng);
+    let argon2 = Argon2::default();
+    let password_hash = argon2
+        .hash_password(password.as_bytes(), &salt)
+        .unwrap()
+        .to_string();
+
+    let rec = sqlx::query!(
+        "INSERT INTO users (username, password_hash) VALUES (?, ?) RETURNING id",
+        username,
+        password_hash
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap();
+
+    rec.id
+}
+
+async fn login(app: &axum::Router, username: &str, password: &str) -> String {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/login")
+                .method("POST")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "username": username,
+                        "password": password
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    json["token"].as_str().unwrap().to_string()
+}
+
+async fn send(
+    app: &axum::Router,
+    token: &str,
+    method: &str,
+    uri: &str,
+    body: Option<serde_json::Value>,
+) -> (StatusCode, serde_json::Value) {
+    let builder = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("Authorization", format!("Bearer {}", token));
+    let request = match body {
+        Some(body) => builder
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap(),
+        None => builder.body(Body::empty()).unwrap(),
+    };
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json = serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null);
+    (status, json)
+}
@@ -0,0 +1,275 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use serde_json::json;
+use server::{
+    auth::JwtKeys,
+    create_router,
+    models::{Account, RecoveryCodes, TotpEnrollment},
+    state::AppState,
+};
+use sqlx::SqlitePool;
+use totp_rs::{Secret, TOTP};
+use tower::ServiceExt; // for `oneshot`
+
+#[sqlx::test]
+async fn test_totp_enrollment_and_login(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    create_user(&pool, "alice", "password1").await;
+    let password_only = json!({ "username": "alice", "password": "password1" });
+    let token = login_token(&app, password_only.clone()).await;
+
+    // 1. Enrolling hands out a secret but changes nothing until it is confirmed
+    let (status, body) = send(&app, &token, "POST", "/api/account/totp", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let enrollment: TotpEnrollment = serde_json::from_value(body).unwrap();
+    assert!(enrollment.otpauth_uri.starts_with("otpauth://totp/"));
+    assert!(enrollment.otpauth_uri.contains(&enrollment.secret));
+    let totp = TOTP::from_url(&enrollment.otpauth_uri).unwrap();
+    assert_eq!(
+        Secret::Encoded(enrollment.secret.clone())
+            .to_bytes()
+            .unwrap(),
+        totp.secret
+    );
+
+    let (status, _) = login(&app, password_only.clone()).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // 2. Confirming needs a valid code and returns recovery codes
+    let (status, _) = send(
+        &app,
+        &token,
+        "POST",
+        "/api/account/totp/confirm",
+        Some(json!({ "code": "000000" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, body) = send(
+        &app,
+        &token,
+        "POST",
+        "/api/account/totp/confirm",
+        Some(json!({ "code": code(&totp, 0) })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let recovery: RecoveryCodes = serde_json::from_value(body).unwrap();
+    assert_eq!(recovery.recovery_codes.len(), 10);
+
+    let (_, body) = send(&app, &token, "GET", "/api/account", None).await;
+    let account: Account = serde_json::from_value(body).unwrap();
+    assert!(account.totp_enabled);
+
+    // 3. The password alone is no longer enough
+    let (status, _) = login(&app, password_only.clone()).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = login(
+        &app,
+        json!({ "username": "alice", "password": "password1", "totp_code": "000000" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // 4. A current code works, but only once
+    let with_code =
+        json!({ "username": "alice", "password": "password1", "totp_code": code(&totp, 1) });
+    let (status, _) = login(&app, with_code.clone()).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = login(&app, with_code).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // 5. A wrong password fails even with a good code
+    let (status, _) = login(
+        &app,
+        json!({ "username": "alice", "password": "nope", "totp_code": code(&totp, 1) }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[sqlx::test]
+async fn test_recovery_codes_and_disable(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    create_user(&pool, "alice", "password1").await;
+    let token = login_token(
+        &app,
+        json!({ "username": "alice", "password": "password1" }),
+    )
+    .await;
+
+    let (_, body) = send(&app, &token, "POST", "/api/account/totp", None).await;
+    let enrollment: TotpEnrollment = serde_json::from_value(body).unwrap();
+    let totp = TOTP::from_url(&enrollment.otpauth_uri).unwrap();
+    let (_, body) = send(
+        &app,
+        &token,
+        "POST",
+        "/api/account/totp/confirm",
+        Some(json!({ "code": code(&totp, 0) })),
+    )
+    .await;
+    let recovery: RecoveryCodes = serde_json::from_value(body).unwrap();
+
+    // 1. Recovery codes are stored hashed
+    let stored = sqlx::query_scalar!("SELECT code_hash FROM recovery_codes")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(stored.len(), 10);
+    assert!(!stored.contains(&recovery.recovery_codes[0]));
+
+    // 2. Each recovery code logs in once
+    let with_recovery = json!({
+        "username": "alice",
+        "password": "password1",
+        "recovery_code": recovery.recovery_codes[0]
+    });
+    let (status, _) = login(&app, with_recovery.clone()).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = login(&app, with_recovery).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // 3. Regenerating replaces the old set
+    let (status, body) = send(
+        &app,
+        &token,
+        "POST",
+        "/api/account/totp/recovery-codes",
+        Some(json!({ "current_password": "password1" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let fresh: RecoveryCodes = serde_json::from_value(body).unwrap();
+    assert_ne!(fresh.recovery_codes, recovery.recovery_codes);
+    let (status, _) = login(
+        &app,
+        json!({ "username": "alice", "password": "password1", "recovery_code": recovery.recovery_codes[1] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // 4. Disabling needs the password and drops the codes
+    let (status, _) = send(
+        &app,
+        &token,
+        "DELETE",
+        "/api/account/totp",
+        Some(json!({ "current_password": "wrong" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send(
+        &app,
+        &token,
+        "DELETE",
+        "/api/account/totp",
+        Some(json!({ "current_password": "password1" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (status, _) = login(
+        &app,
+        json!({ "username": "alice", "password": "password1" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let remaining = sqlx::query_scalar!("SELECT COUNT(*) FROM recovery_codes")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(remaining, 0);
+}
+
+// Helpers (Duplicated for isolation as requested)
+async fn create_user(pool: &SqlitePool, username: &str, password: &str) -> i64 {
+    use argon2::{
+        Argon2,
+        password_hash::{PasswordHasher, SaltString, rand_core::OsRng},
+    };
+
+    let salt = SaltString::generate(&mut OsRng);
+    let argon2 = Argon2::default();
+    let password_hash = argon2
+        .hash_password(password.as_bytes(), &salt)
+        .unwrap()
+        .to_string();
+
+    let rec = sqlx::query!(
+        "INSERT INTO users (username, password_hash) VALUES (?, ?) RETURNING id",
+        username,
+        password_hash
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap();
+
+    rec.id
+}
+
+async fn login(app: &axum::Router, body: serde_json::Value) -> (StatusCode, serde_json::Value) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/login")
+                .method("POST")
+                .header("Content-Type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json = serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null);
+    (status, json)
+}
+
+async fn login_token(app: &axum::Router, body: serde_json::Value) -> String {
+    let (status, json) = login(app, body).await;
+    assert_eq!(status, StatusCode::OK);
+    json["token"].as_str().unwrap().to_string()
+}
+
+// Code for the time step `offset` steps from now
+fn code(totp: &TOTP, offset: i64) -> String {
+    let now = chrono::Utc::now().timestamp() + offset * 30;
+    totp.generate(now as u64)
+}
+
+async fn send(
+    app: &axum::Router,
+    token: &str,
+    method: &str,
+    uri: &str,
+    body: Option<serde_json::Value>,
+) -> (StatusCode, serde_json::Value) {
+    let builder = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("Authorization", format!("Bearer {}", token));
+    let request = match body {
+        Some(body) => builder
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap(),
+        None => builder.body(Body::empty()).unwrap(),
+    };
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json = serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null);
+    (status, json)
+}
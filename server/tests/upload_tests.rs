@@ -0,0 +1,686 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode, header},
+};
+use image::{DynamicImage, ImageFormat, RgbImage, RgbaImage};
+use serde_json::json;
+use server::{
+    auth::JwtKeys,
+    create_router,
+    state::AppState,
+    upload::{UploadSettings, delete_image, upload_key, uploaded_files},
+};
+use sqlx::SqlitePool;
+use std::io::Cursor;
+use std::path::Path;
+use tower::ServiceExt; // for `oneshot`
+
+#[sqlx::test]
+async fn test_upload_creates_variants(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    create_user(&pool, "alice", "password1").await;
+    let token = login(&app, "alice", "password1").await.unwrap();
+    std::fs::create_dir_all("uploads").unwrap();
+
+    // 1. Every size comes back, scaled to fit without changing the shape
+    let (status, body) = upload(&app, &token, &png(1000, 500)).await;
+    assert_eq!(status, StatusCode::OK);
+    let url = body["url"].as_str().unwrap().to_string();
+    assert_eq!(body["variants"]["full"], url.as_str());
+
+    let thumbnail = body["variants"]["thumbnail"].as_str().unwrap().to_string();
+    let card = body["variants"]["card"].as_str().unwrap().to_string();
+    assert_eq!(dimensions(&url), (1000, 500));
+    assert_eq!(dimensions(&thumbnail), (160, 80));
+    assert_eq!(dimensions(&card), (640, 320));
+
+    // 2. Items list them next to image_url
+    let (_, item) = send(
+        &app,
+        &token,
+        "POST",
+        "/api/items",
+        Some(json!({ "category": "Photos", "name": "Beach", "image_url": url })),
+    )
+    .await;
+    assert_eq!(item["image_variants"]["thumbnail"], thumbnail.as_str());
+
+    let (_, item) = send(
+        &app,
+        &token,
+        "POST",
+        "/api/items",
+        Some(json!({ "category": "Photos", "name": "Hotlinked", "image_url": "https://example.com/a.jpg" })),
+    )
+    .await;
+    assert!(item["image_variants"].is_null());
+
+    // 3. Small images aren't enlarged
+    let (_, body) = upload(&app, &token, &png(100, 50)).await;
+    let small_card = body["variants"]["card"].as_str().unwrap();
+    assert_eq!(dimensions(small_card), (100, 50));
+    delete_image(body["url"].as_str().unwrap()).await.unwrap();
+
+    // 4. Deleting the item removes every size
+    let (_, items) = send(&app, &token, "GET", "/api/items?category=Photos", None).await;
+    let beach = items
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|i| i["name"] == "Beach")
+        .unwrap();
+    let uri = format!("/api/items/{}", beach["id"].as_str().unwrap());
+    let (status, _) = send(&app, &token, "DELETE", &uri, None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    for file in [&url, &thumbnail, &card] {
+        assert!(!Path::new(&file[1..]).exists());
+    }
+    let key = upload_key(&url).trim_start_matches("/uploads/").to_string();
+    let leftovers = std::fs::read_dir("uploads")
+        .unwrap()
+        .filter(|entry| {
+            let name = entry.as_ref().unwrap().file_name();
+            name.to_string_lossy().starts_with(&key)
+        })
+        .count();
+    assert_eq!(leftovers, 0);
+}
+
+#[sqlx::test]
+async fn test_upload_formats(pool: SqlitePool) {
+    // The defaults: AVIF and WebP
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    create_user(&pool, "alice", "password1").await;
+    let token = login(&app, "alice", "password1").await.unwrap();
+    std::fs::create_dir_all("uploads").unwrap();
+
+    // 1. Transparency survives as PNG, with smaller copies for browsers
+    //    that ask for them
+    let logo = RgbaImage::from_fn(64, 64, |x, _| {
+        if x < 32 {
+            image::Rgba([20, 80, 200, 255])
+        } else {
+            image::Rgba([0, 0, 0, 0])
+        }
+    });
+    let (status, body) = upload(&app, &token, &encode(DynamicImage::ImageRgba8(logo))).await;
+    assert_eq!(status, StatusCode::OK);
+    let url = body["url"].as_str().unwrap().to_string();
+    assert!(url.ends_with(".png"));
+    let saved = image::open(&url[1..]).unwrap();
+    assert_eq!(saved.to_rgba8().get_pixel(48, 10)[3], 0);
+
+    let headers = fetch(&app, &url, "image/avif,image/webp,*/*;q=0.8").await;
+    let content_type = headers[header::CONTENT_TYPE].to_str().unwrap();
+    assert!(content_type == "image/avif" || content_type == "image/webp");
+    assert_eq!(headers[header::VARY], "accept");
+
+    let headers = fetch(&app, &url, "image/webp,*/*").await;
+    assert_eq!(headers[header::CONTENT_TYPE], "image/webp");
+
+    let headers = fetch(&app, &url, "image/webp;q=0,*/*").await;
+    assert_eq!(headers[header::CONTENT_TYPE], "image/png");
+
+    let headers = fetch(&app, body["variants"]["thumbnail"].as_str().unwrap(), "*/*").await;
+    assert_eq!(headers[header::CONTENT_TYPE], "image/png");
+
+    // 2. An alpha channel that is fully opaque doesn't stop JPEG
+    let opaque = RgbaImage::from_pixel(40, 30, image::Rgba([200, 30, 30, 255]));
+    let (_, body) = upload(&app, &token, &encode(DynamicImage::ImageRgba8(opaque))).await;
+    let opaque_url = body["url"].as_str().unwrap().to_string();
+    assert!(opaque_url.ends_with(".jpg"));
+
+    // 3. For a photo, lossy AVIF beats the JPEG but lossless WebP doesn't,
+    //    so only the AVIF is kept
+    let noise = RgbImage::from_fn(128, 128, |x, y| {
+        let n = (x * 7919 + y * 104729) ^ (x * y * 31);
+        image::Rgb([(n % 251) as u8, (n % 241) as u8, (n % 239) as u8])
+    });
+    let (_, body) = upload(&app, &token, &encode(DynamicImage::ImageRgb8(noise))).await;
+    let photo_url = body["url"].as_str().unwrap().to_string();
+    let key = &upload_key(&photo_url)[1..];
+    assert!(!Path::new(&format!("{}.webp", key)).exists());
+    assert!(Path::new(&format!("{}.avif", key)).exists());
+    let headers = fetch(&app, &photo_url, "image/avif,image/webp,*/*").await;
+    assert_eq!(headers[header::CONTENT_TYPE], "image/avif");
+    let headers = fetch(&app, &photo_url, "image/webp,*/*").await;
+    assert_eq!(headers[header::CONTENT_TYPE], "image/jpeg");
+
+    for url in [&url, &opaque_url, &photo_url] {
+        delete_image(url).await.unwrap();
+    }
+}
+
+fn encode(img: DynamicImage) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    img.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .unwrap();
+    bytes
+}
+
+async fn fetch(app: &axum::Router, uri: &str, accept: &str) -> axum::http::HeaderMap {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(uri)
+                .header(header::ACCEPT, accept)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    response.headers().clone()
+}
+
+#[sqlx::test]
+async fn test_upload_applies_exif_orientation(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    create_user(&pool, "alice", "password1").await;
+    let token = login(&app, "alice", "password1").await.unwrap();
+    std::fs::create_dir_all("uploads").unwrap();
+
+    // Every orientation a camera can record comes out the same way up
+    for orientation in 1..=8 {
+        let (status, body) = upload(&app, &token, &oriented_fixture(orientation)).await;
+        assert_eq!(status, StatusCode::OK, "orientation {}", orientation);
+        let url = body["url"].as_str().unwrap();
+
+        let saved = std::fs::read(&url[1..]).unwrap();
+        let img = image::load_from_memory(&saved).unwrap().to_rgb8();
+        assert_eq!(img.dimensions(), (64, 32), "orientation {}", orientation);
+        assert!(is_red(img.get_pixel(8, 4)), "orientation {}", orientation);
+        assert!(!is_red(img.get_pixel(56, 4)), "orientation {}", orientation);
+        assert!(!is_red(img.get_pixel(8, 28)), "orientation {}", orientation);
+
+        // The tag itself is stripped with the rest of the metadata
+        assert!(!saved.windows(6).any(|w| w == b"Exif\0\0"));
+
+        delete_image(url).await.unwrap();
+    }
+}
+
+fn is_red(pixel: &image::Rgb<u8>) -> bool {
+    pixel[0] > 150 && pixel[2] < 100
+}
+
+/// A JPEG tagged with EXIF `orientation` whose pixels are stored so that,
+/// once the tag is applied, it shows a 64x32 image with a red top-left corner.
+fn oriented_fixture(orientation: u16) -> Vec<u8> {
+    let upright = DynamicImage::ImageRgb8(RgbImage::from_fn(64, 32, |x, y| {
+        if x < 32 && y < 16 {
+            image::Rgb([255, 0, 0])
+        } else {
+            image::Rgb([0, 0, 255])
+        }
+    }));
+
+    // The inverse of what each orientation asks a viewer to do
+    let stored = match orientation {
+        1 => upright,
+        2 => upright.fliph(),
+        3 => upright.rotate180(),
+        4 => upright.flipv(),
+        5 => upright.rotate90().fliph(),
+        6 => upright.rotate270(),
+        7 => upright.rotate270().fliph(),
+        8 => upright.rotate90(),
+        _ => unreachable!(),
+    };
+
+    let mut jpeg = Vec::new();
+    stored
+        .write_to(&mut Cursor::new(&mut jpeg), ImageFormat::Jpeg)
+        .unwrap();
+
+    // APP1 segment with a big-endian TIFF header and a single IFD entry:
+    // tag 0x0112 (Orientation), type SHORT, count 1
+    let mut tiff = b"MM\0\x2a\0\0\0\x08\0\x01\x01\x12\0\x03\0\0\0\x01".to_vec();
+    tiff.extend_from_slice(&orientation.to_be_bytes());
+    tiff.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+    let mut app1 = vec![0xFF, 0xE1];
+    app1.extend_from_slice(&((2 + 6 + tiff.len()) as u16).to_be_bytes());
+    app1.extend_from_slice(b"Exif\0\0");
+    app1.extend_from_slice(&tiff);
+
+    // Right after the SOI marker
+    jpeg.splice(2..2, app1);
+    jpeg
+}
+
+#[sqlx::test]
+async fn test_upload_ownership(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    let alice_id = create_user(&pool, "alice", "password1").await;
+    create_user(&pool, "bob", "password2").await;
+    let alice = login(&app, "alice", "password1").await.unwrap();
+    let bob = login(&app, "bob", "password2").await.unwrap();
+    std::fs::create_dir_all("uploads").unwrap();
+
+    // 1. Uploads are recorded against whoever made them
+    let (_, body) = upload(&app, &alice, &png(120, 80)).await;
+    let url = body["url"].as_str().unwrap().to_string();
+    let (owner, size, width, height, hash) = sqlx::query_as::<_, (i64, i64, i64, i64, String)>(
+        "SELECT user_id, size, width, height, hash FROM uploads WHERE url = ?",
+    )
+    .bind(&url)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(owner, alice_id);
+    // Every file the upload wrote counts, not only the full size one
+    let on_disk: u64 = uploaded_files()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|(file, _)| upload_key(file) == upload_key(&url))
+        .map(|(_, metadata)| metadata.len())
+        .sum();
+    assert_eq!(size as u64, on_disk);
+    assert!(size as u64 > std::fs::metadata(&url[1..]).unwrap().len());
+    assert_eq!((width, height), (120, 80));
+    assert_eq!(hash.len(), 64);
+
+    // 2. Nobody else can put it on their items or covers
+    let (status, _) = send(
+        &app,
+        &bob,
+        "POST",
+        "/api/items",
+        Some(json!({ "category": "Mine", "name": "Taken", "image_url": url })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = send(
+        &app,
+        &bob,
+        "POST",
+        "/api/categories",
+        Some(json!({ "name": "Covers", "cover_image_url": url })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (_, bobs_item) = send(
+        &app,
+        &bob,
+        "POST",
+        "/api/items",
+        Some(json!({ "category": "Mine", "name": "Plain" })),
+    )
+    .await;
+    let (status, _) = send(
+        &app,
+        &bob,
+        "PATCH",
+        &format!("/api/items/{}", bobs_item["id"].as_str().unwrap()),
+        Some(json!({ "image_url": url })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Files that were never uploaded can't be pointed at either
+    let (status, _) = send(
+        &app,
+        &alice,
+        "POST",
+        "/api/items",
+        Some(json!({ "category": "Photos", "name": "Stray", "image_url": "/uploads/stray.jpg" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // 3. The owner can use it twice, and an editor can keep it when saving
+    let mut items = Vec::new();
+    for name in ["First", "Second"] {
+        let (status, item) = send(
+            &app,
+            &alice,
+            "POST",
+            "/api/items",
+            Some(json!({ "category": "Photos", "name": name, "image_url": url })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        items.push(item["id"].as_str().unwrap().to_string());
+    }
+    let (_, item) = send(
+        &app,
+        &alice,
+        "GET",
+        &format!("/api/items/{}", items[0]),
+        None,
+    )
+    .await;
+    send(
+        &app,
+        &alice,
+        "POST",
+        &format!(
+            "/api/categories/{}/members",
+            item["category_id"].as_str().unwrap()
+        ),
+        Some(json!({ "username": "bob", "role": "editor" })),
+    )
+    .await;
+    let (status, _) = send(
+        &app,
+        &bob,
+        "PATCH",
+        &format!("/api/items/{}", items[0]),
+        Some(json!({ "name": "Renamed", "image_url": url })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // 4. The file goes with the last item using it
+    send(
+        &app,
+        &alice,
+        "DELETE",
+        &format!("/api/items/{}", items[0]),
+        None,
+    )
+    .await;
+    assert!(Path::new(&url[1..]).exists());
+    send(
+        &app,
+        &alice,
+        "DELETE",
+        &format!("/api/items/{}", items[1]),
+        None,
+    )
+    .await;
+    assert!(!Path::new(&url[1..]).exists());
+    let tracked: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM uploads")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(tracked, 0);
+
+    // 5. A path an item refers to without having been uploaded is left alone
+    let stray = format!("uploads/stray-{}.jpg", alice_id);
+    std::fs::write(&stray, b"jpeg").unwrap();
+    sqlx::query("UPDATE items SET image_url = ? WHERE id = ?")
+        .bind(format!("/{}", stray))
+        .bind(bobs_item["id"].as_str().unwrap())
+        .execute(&pool)
+        .await
+        .unwrap();
+    send(
+        &app,
+        &bob,
+        "DELETE",
+        &format!("/api/items/{}", bobs_item["id"].as_str().unwrap()),
+        None,
+    )
+    .await;
+    assert!(Path::new(&stray).exists());
+    std::fs::remove_file(stray).unwrap();
+
+    // 6. An upload on an item in a shared category outlives its uploader
+    let (_, body) = upload(&app, &bob, &png(90, 60)).await;
+    let bobs_url = body["url"].as_str().unwrap().to_string();
+    let (status, _) = send(
+        &app,
+        &bob,
+        "POST",
+        "/api/items",
+        Some(json!({ "category": "Photos", "category_id": item["category_id"], "name": "Gift", "image_url": bobs_url })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(
+        &app,
+        &bob,
+        "DELETE",
+        "/api/account",
+        Some(json!({ "current_password": "password2" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert!(Path::new(&bobs_url[1..]).exists());
+    let owner: i64 = sqlx::query_scalar("SELECT user_id FROM uploads WHERE url = ?")
+        .bind(&bobs_url)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(owner, alice_id);
+    delete_image(&bobs_url).await.unwrap();
+}
+
+#[sqlx::test]
+async fn test_upload_limits(pool: SqlitePool) {
+    let settings = UploadSettings {
+        max_bytes: 8 * 1024,
+        max_dimension: 200,
+        ..UploadSettings::default()
+    };
+    let app = create_router(
+        AppState::new(pool.clone(), JwtKeys::random()).with_upload_settings(settings),
+    );
+    create_user(&pool, "alice", "password1").await;
+    let token = login(&app, "alice", "password1").await.unwrap();
+    std::fs::create_dir_all("uploads").unwrap();
+
+    // 1. Only the common web formats are decoded
+    let (status, _) = upload(&app, &token, b"just some text").await;
+    assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+    let mut bmp = Vec::new();
+    RgbImage::from_pixel(10, 10, image::Rgb([1, 2, 3]))
+        .write_to(&mut Cursor::new(&mut bmp), ImageFormat::Bmp)
+        .unwrap();
+    let (status, _) = upload(&app, &token, &bmp).await;
+    assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+    // 2. Neither side may exceed the maximum dimension
+    let (status, _) = upload(&app, &token, &png(300, 100)).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    let (status, _) = upload(&app, &token, &png(100, 201)).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+
+    let (status, body) = upload(&app, &token, &png(200, 100)).await;
+    assert_eq!(status, StatusCode::OK);
+    delete_image(body["url"].as_str().unwrap()).await.unwrap();
+
+    // 3. Files over the byte limit are refused, as are bodies far past it
+    let noise = RgbImage::from_fn(100, 100, |x, y| {
+        let n = (x * 7919 + y * 104729) ^ (x * y * 31);
+        image::Rgb([(n % 251) as u8, (n % 241) as u8, (n % 239) as u8])
+    });
+    let noisy = encode(DynamicImage::ImageRgb8(noise));
+    assert!(noisy.len() > 8 * 1024);
+    let (status, _) = upload(&app, &token, &noisy).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+
+    let (status, _) = upload(&app, &token, &vec![0; 200 * 1024]).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[sqlx::test]
+async fn test_upload_rejects_decompression_bombs(pool: SqlitePool) {
+    let app = create_router(AppState::new(pool.clone(), JwtKeys::random()));
+    create_user(&pool, "alice", "password1").await;
+    let token = login(&app, "alice", "password1").await.unwrap();
+
+    // A few hundred bytes claiming to be 100000x100000 pixels
+    let (status, body) = upload(&app, &token, &png_header(100_000, 100_000, 8)).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    assert!(body.is_null());
+
+    // Within the dimension limit, but 16-bit RGBA and its 8-bit copy would
+    // need 768 MiB
+    let (status, _) = upload(&app, &token, &png_header(8192, 8192, 16)).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+/// An RGBA PNG with a valid header and a token amount of pixel data.
+fn png_header(width: u32, height: u32, bit_depth: u8) -> Vec<u8> {
+    fn chunk(png: &mut Vec<u8>, kind: &[u8], data: &[u8]) {
+        png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        let start = png.len();
+        png.extend_from_slice(kind);
+        png.extend_from_slice(data);
+        let crc = png[start..].iter().fold(!0u32, |mut crc, &byte| {
+            crc ^= byte as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 == 1 {
+                    (crc >> 1) ^ 0xEDB8_8320
+                } else {
+                    crc >> 1
+                };
+            }
+            crc
+        });
+        png.extend_from_slice(&(!crc).to_be_bytes());
+    }
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    let mut ihdr = Vec::new();
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    ihdr.extend_from_slice(&[bit_depth, 6, 0, 0, 0]);
+    chunk(&mut png, b"IHDR", &ihdr);
+    // An empty zlib stream
+    chunk(
+        &mut png,
+        b"IDAT",
+        &[0x78, 0x9c, 0x03, 0x00, 0x00, 0x00, 0x00, 0x01],
+    );
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn png(width: u32, height: u32) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    RgbImage::from_pixel(width, height, image::Rgb([200, 120, 40]))
+        .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .unwrap();
+    bytes
+}
+
+fn dimensions(url: &str) -> (u32, u32) {
+    let img = image::open(&url[1..]).unwrap();
+    (img.width(), img.height())
+}
+
+async fn upload(app: &axum::Router, token: &str, bytes: &[u8]) -> (StatusCode, serde_json::Value) {
+    let boundary = "upload-test-boundary";
+    let mut body = format!(
+        "--{boundary}\r\nContent-Disposition: form-data; name=\"image\"; filename=\"photo.png\"\r\nContent-Type: image/png\r\n\r\n"
+    )
+    .into_bytes();
+    body.extend_from_slice(bytes);
+    body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/upload")
+                .method("POST")
+                .header("Authorization", format!("Bearer {}", token))
+                .header(
+                    "Content-Type",
+                    format!("multipart/form-data; boundary={boundary}"),
+                )
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json = serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null);
+    (status, json)
+}
+
+// Helpers (Duplicated for isolation as requested)
+async fn create_user(pool: &SqlitePool, username: &str, password: &str) -> i64 {
+    use argon2::{
+        Argon2,
+        password_hash::{PasswordHasher, SaltString, rand_core::OsRng},
+    };
+
+    let salt = SaltString::generate(&mut OsRng);
+    let argon2 = Argon2::default();
+    let password_hash = argon2
+        .hash_password(password.as_bytes(), &salt)
+        .unwrap()
+        .to_string();
+
+    let rec = sqlx::query!(
+        "INSERT INTO users (username, password_hash) VALUES (?, ?) RETURNING id",
+        username,
+        password_hash
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap();
+
+    rec.id
+}
+
+async fn login(app: &axum::Router, username: &str, password: &str) -> Option<String> {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/login")
+                .method("POST")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "username": username,
+                        "password": password
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    if response.status() != StatusCode::OK {
+        return None;
+    }
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    Some(json["token"].as_str().unwrap().to_string())
+}
+
+async fn send(
+    app: &axum::Router,
+    token: &str,
+    method: &str,
+    uri: &str,
+    body: Option<serde_json::Value>,
+) -> (StatusCode, serde_json::Value) {
+    let builder = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("Authorization", format!("Bearer {}", token));
+    let request = match body {
+        Some(body) => builder
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap(),
+        None => builder.body(Body::empty()).unwrap(),
+    };
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json = serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null);
+    (status, json)
+}